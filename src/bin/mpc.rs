@@ -8,28 +8,46 @@ extern crate clap;
 extern crate mpd;
 
 use std::borrow::Cow;
-use std::{io, process};
+use std::{cmp, env, fmt};
+use std::io::{self, Write};
+use std::process;
 
-use clap::{App, Arg, Format, SubCommand};
-use mpd::{Connection, Command, Extra, Mode, Song, State, Status, parse};
+use clap::{App, AppSettings, Arg, Format, SubCommand};
+use mpd::filter::{Filter, Op};
+use mpd::{Connection, Command, Entry, Error, Extra, Group, Mode, Playlist, Position, Range,
+          SeekTarget, Song, State, Stats, Status, StickerOp, Subsystem, parse};
 
 fn main() {
-    // TODO report I/O errors
-    run().unwrap();
+    if let Err(e) = run() {
+        die(e);
+    }
 }
 
-fn run() -> io::Result<()> {
+fn run() -> Result<(), Error> {
     // Possible values for boolean arguments
     static VALUES: &'static [&'static str] = &["0", "1", "false", "no", "off", "on", "true", "yes"];
 
     let matches = &App::new("mpc")
+                          .arg(Arg::with_name("host")
+                                   .help("Connect to server on <host> (overrides $MPD_HOST)")
+                                   .long("host")
+                                   .takes_value(true))
+                          .arg(Arg::with_name("port")
+                                   .help("Connect to server port <port> (overrides $MPD_PORT)")
+                                   .long("port")
+                                   .takes_value(true))
                           .arg(Arg::with_name("quiet")
                                    .help("Suppress status message")
                                    .long("quiet")
                                    .short("q"))
                           .subcommand(SubCommand::with_name("add")
-                                          .about("Add song to the current playlist")
-                                          .arg(Arg::with_name("uri").required(true)))
+                                          .about("Add songs to the current playlist")
+                                          .arg(Arg::with_name("uri")
+                                                   .multiple(true)
+                                                   .required(true)))
+                          .subcommand(SubCommand::with_name("channels")
+                                          .about("List the channels that other clients are \
+                                                  subscribed to"))
                           .subcommand(SubCommand::with_name("clear")
                                           .about("Clear the current playlist"))
                           .subcommand(SubCommand::with_name("consume")
@@ -37,11 +55,79 @@ fn run() -> io::Result<()> {
                                           .arg(Arg::with_name("state")
                                                    .possible_values(VALUES)
                                                    .required(true)))
+                          .subcommand(SubCommand::with_name("crop")
+                                          .about("Remove all but the currently playing song"))
+                          .subcommand(SubCommand::with_name("del")
+                                          .about("Remove songs, by position (N) or range (N-M), \
+                                                  from the current playlist")
+                                          .arg(Arg::with_name("songpos")
+                                                   .multiple(true)
+                                                   .required(true)))
+                          .subcommand(SubCommand::with_name("disable")
+                                          .about("Disable outputs, by number or name")
+                                          .arg(Arg::with_name("output")
+                                                   .multiple(true)
+                                                   .required(true)))
+                          .subcommand(SubCommand::with_name("enable")
+                                          .about("Enable outputs, by number or name")
+                                          .arg(Arg::with_name("output")
+                                                   .multiple(true)
+                                                   .required(true)))
+                          .subcommand(SubCommand::with_name("find")
+                                          .about("Search the database for songs that exactly match \
+                                                  the query")
+                                          .arg(Arg::with_name("query")
+                                                   .help("<tag> <value> pairs or a filter \
+                                                          expression")
+                                                   .multiple(true)
+                                                   .required(true)))
+                          .subcommand(SubCommand::with_name("findadd")
+                                          .about("Add the songs that exactly match the query to \
+                                                  the current playlist")
+                                          .arg(Arg::with_name("query")
+                                                   .help("<tag> <value> pairs or a filter \
+                                                          expression")
+                                                   .multiple(true)
+                                                   .required(true)))
+                          .subcommand(SubCommand::with_name("insert")
+                                          .about("Insert songs right after the currently \
+                                                  playing song")
+                                          .arg(Arg::with_name("uri")
+                                                   .multiple(true)
+                                                   .required(true)))
+                          .subcommand(SubCommand::with_name("list")
+                                          .about("List the unique values of a tag, optionally \
+                                                  filtered by a query and grouped by other tags")
+                                          .arg(Arg::with_name("tag").required(true))
+                                          .arg(Arg::with_name("query")
+                                                   .help("<tag> <value> pairs or a filter \
+                                                          expression, followed by any number \
+                                                          of `group <tag>`")
+                                                   .multiple(true)))
                           .subcommand(SubCommand::with_name("listall")
                                           .about("List all songs in the music dir")
                                           .arg(Arg::with_name("uri")))
+                          .subcommand(SubCommand::with_name("load")
+                                          .about("Load stored playlists into the current \
+                                                  playlist")
+                                          .arg(Arg::with_name("name")
+                                                   .multiple(true)
+                                                   .required(true)))
+                          .subcommand(SubCommand::with_name("ls")
+                                          .about("List the contents of a directory of the music \
+                                                  dir")
+                                          .arg(Arg::with_name("uri")))
+                          .subcommand(SubCommand::with_name("lsplaylists")
+                                          .about("List the stored playlists"))
+                          .subcommand(SubCommand::with_name("move")
+                                          .about("Move a song, or a range of songs (N-M), to \
+                                                  another position in the current playlist")
+                                          .arg(Arg::with_name("from").required(true))
+                                          .arg(Arg::with_name("to").required(true)))
                           .subcommand(SubCommand::with_name("next")
                                           .about("Play the next song in the current playlist"))
+                          .subcommand(SubCommand::with_name("outputs")
+                                          .about("Show the audio outputs"))
                           .subcommand(SubCommand::with_name("pause")
                                           .about("Pauses the currently playing song"))
                           .subcommand(SubCommand::with_name("play")
@@ -51,6 +137,13 @@ fn run() -> io::Result<()> {
                                           .about("Prints the current playlist"))
                           .subcommand(SubCommand::with_name("prev")
                                           .about("Play the previous song in the current playlist"))
+                          .subcommand(SubCommand::with_name("prio")
+                                          .about("Set the priority (0-255) of songs, by position \
+                                                  (N) or range (N-M), for random mode")
+                                          .arg(Arg::with_name("priority").required(true))
+                                          .arg(Arg::with_name("songpos")
+                                                   .multiple(true)
+                                                   .required(true)))
                           .subcommand(SubCommand::with_name("random")
                                           .about("Set random mode")
                                           .arg(Arg::with_name("state")
@@ -61,30 +154,114 @@ fn run() -> io::Result<()> {
                                           .arg(Arg::with_name("state")
                                                    .possible_values(VALUES)
                                                    .required(true)))
+                          .subcommand(SubCommand::with_name("rm")
+                                          .about("Remove a stored playlist")
+                                          .arg(Arg::with_name("name").required(true)))
+                          .subcommand(SubCommand::with_name("save")
+                                          .about("Save the current playlist as a stored playlist")
+                                          .arg(Arg::with_name("name").required(true)))
+                          .subcommand(SubCommand::with_name("search")
+                                          .about("Search the database for songs that match the \
+                                                  query (case insensitive substring match)")
+                                          .arg(Arg::with_name("query")
+                                                   .help("<tag> <value> pairs or a filter \
+                                                          expression")
+                                                   .multiple(true)
+                                                   .required(true)))
+                          .subcommand(SubCommand::with_name("searchadd")
+                                          .about("Add the songs that match the query, as `search` \
+                                                  does, to the current playlist")
+                                          .arg(Arg::with_name("query")
+                                                   .help("<tag> <value> pairs or a filter \
+                                                          expression")
+                                                   .multiple(true)
+                                                   .required(true)))
+                          .subcommand(SubCommand::with_name("searchplay")
+                                          .about("Play the first song of the current playlist that \
+                                                  matches the query, as `search` does")
+                                          .arg(Arg::with_name("query")
+                                                   .help("<tag> <value> pairs or a filter \
+                                                          expression")
+                                                   .multiple(true)
+                                                   .required(true)))
+                          .subcommand(SubCommand::with_name("seek")
+                                          .about("Seek to the specified position in the current \
+                                                  song")
+                                          .setting(AppSettings::AllowLeadingHyphen)
+                                          .arg(Arg::with_name("time")
+                                                   .help("[+-][HH:]MM:SS, seconds or 0-100%")
+                                                   .required(true)))
+                          .subcommand(SubCommand::with_name("sendmessage")
+                                          .about("Send a message to a channel")
+                                          .arg(Arg::with_name("channel").required(true))
+                                          .arg(Arg::with_name("text").required(true)))
+                          .subcommand(SubCommand::with_name("shuffle")
+                                          .about("Shuffle the current playlist"))
                           .subcommand(SubCommand::with_name("single")
                                           .about("Set single mode")
                                           .arg(Arg::with_name("state")
                                                    .possible_values(VALUES)
                                                    .required(true)))
+                          .subcommand(SubCommand::with_name("stats")
+                                          .about("Display statistics about MPD"))
+                          .subcommand(SubCommand::with_name("sticker")
+                                          .about("Manage the stickers of a song, or find the \
+                                                  stickers of the songs within a directory")
+                                          .arg(Arg::with_name("uri").required(true))
+                                          .arg(Arg::with_name("action")
+                                                   .possible_values(&["delete", "find", "get",
+                                                                      "list", "set"])
+                                                   .required(true))
+                                          .arg(Arg::with_name("args")
+                                                   .help("<name> for delete and get, <name> \
+                                                          <value> for set, <name> [=|<|> \
+                                                          <value>] for find")
+                                                   .multiple(true)))
                           .subcommand(SubCommand::with_name("stop")
                                           .about("Stop the currently playing playlist"))
+                          .subcommand(SubCommand::with_name("subscribe")
+                                          .about("Subscribe to a channel and print the messages \
+                                                  received on it until interrupted")
+                                          .arg(Arg::with_name("channel").required(true)))
+                          .subcommand(SubCommand::with_name("swap")
+                                          .about("Swap two songs in the current playlist")
+                                          .arg(Arg::with_name("pos1").required(true))
+                                          .arg(Arg::with_name("pos2").required(true)))
+                          .subcommand(SubCommand::with_name("toggleoutput")
+                                          .about("Toggle outputs, by number or name")
+                                          .arg(Arg::with_name("output")
+                                                   .multiple(true)
+                                                   .required(true)))
                           .subcommand(SubCommand::with_name("update")
                                           .about("Scan music directory for updates")
                                           .arg(Arg::with_name("uri")))
                           .subcommand(SubCommand::with_name("version")
                                           .about("Report version of MPD"))
+                          .subcommand(SubCommand::with_name("waitmessage")
+                                          .about("Wait for a message on a channel and print it")
+                                          .arg(Arg::with_name("channel").required(true)))
                           .subcommand(SubCommand::with_name("volume")
                                           .about("Set volume")
                                           .arg(Arg::with_name("level").required(true)))
                           .get_matches();
 
+    let host = matches.value_of("host")
+                      .map(String::from)
+                      .or_else(|| env::var("MPD_HOST").ok())
+                      .unwrap_or_else(|| String::from("localhost"));
+    let port = matches.value_of("port")
+                      .map(String::from)
+                      .or_else(|| env::var("MPD_PORT").ok())
+                      .map(|s| s.parse().unwrap_or_else(|_| invalid_value(&s, matches.usage())))
+                      .unwrap_or(6600);
+
     let conn_opt = &mut None;
     let mut quiet = matches.is_present("quiet");
 
     let subcommand = matches.subcommand();
 
     if !subcommand.0.is_empty() {
-        let conn = try!(connect(conn_opt));
+        let conn = try!(connect(conn_opt, &host, port));
 
         match subcommand {
             // Boolean commands
@@ -119,12 +296,14 @@ fn run() -> io::Result<()> {
             (cmd @ "next", _) |
             (cmd @ "pause", _) |
             (cmd @ "prev", _) |
+            (cmd @ "shuffle", _) |
             (cmd @ "stop", _) => {
                 let cmd = match cmd {
                     "clear" => Command::Clear,
                     "next" => Command::Next,
                     "pause" => Command::Pause { state: true },
                     "prev" => Command::Previous,
+                    "shuffle" => Command::Shuffle { range: None },
                     "stop" => Command::Stop,
                     _ => unreachable!(),
                 };
@@ -132,9 +311,274 @@ fn run() -> io::Result<()> {
                 try!(conn.send(cmd));
                 try!(conn.recv());
             }
-            // Commands with a single required argument
             ("add", Some(matches)) => {
-                try!(conn.send(Command::Add { uri: matches.value_of("uri").unwrap() }));
+                let cmds = matches.values_of("uri")
+                                  .unwrap()
+                                  .map(|uri| Command::Add { uri: uri })
+                                  .collect::<Vec<_>>();
+
+                try!(conn.send_list(&cmds));
+                try!(conn.recv_list());
+            }
+            ("insert", Some(matches)) => {
+                let next = {
+                    try!(conn.send(Command::Status));
+                    let status = Status::parse(try!(conn.recv()))
+                                     .unwrap_or_else(|e| parse::bug(e));
+
+                    status.extra.map(|extra| extra.pos + 1)
+                };
+
+                let cmds = matches.values_of("uri")
+                                  .unwrap()
+                                  .zip(0..)
+                                  .map(|(uri, i)| {
+                                      Command::AddId {
+                                          uri: uri,
+                                          position: next.map(|pos| Position::Absolute(pos + i)),
+                                      }
+                                  })
+                                  .collect::<Vec<_>>();
+
+                try!(conn.send_list(&cmds));
+                try!(conn.recv_list());
+            }
+            ("load", Some(matches)) => {
+                let cmds = matches.values_of("name")
+                                  .unwrap()
+                                  .map(|name| {
+                                      Command::Load {
+                                          name: name,
+                                          range: None,
+                                          position: None,
+                                      }
+                                  })
+                                  .collect::<Vec<_>>();
+
+                try!(conn.send_list(&cmds));
+                try!(conn.recv_list());
+            }
+            (cmd @ "findadd", Some(matches)) |
+            (cmd @ "searchadd", Some(matches)) => {
+                let op = if cmd == "findadd" {
+                    Op::Eq
+                } else {
+                    Op::Contains
+                };
+                let filter = &filter(matches.values_of("query").unwrap().collect(),
+                                     op,
+                                     matches.usage());
+
+                try!(conn.send(if cmd == "findadd" {
+                    Command::FindAdd {
+                        filter: filter,
+                        sort: None,
+                        window: None,
+                    }
+                } else {
+                    Command::SearchAdd {
+                        filter: filter,
+                        sort: None,
+                        window: None,
+                    }
+                }));
+                try!(conn.recv());
+            }
+            ("searchplay", Some(matches)) => {
+                let filter = &filter(matches.values_of("query").unwrap().collect(),
+                                     Op::Contains,
+                                     matches.usage());
+
+                // `window` is only supported by MPD 0.24 and newer, the rest of the results are
+                // discarded when the records are dropped
+                let pos = {
+                    try!(conn.send(Command::PlaylistSearch {
+                        filter: filter,
+                        sort: None,
+                        window: None,
+                    }));
+
+                    match conn.recv_records().next() {
+                        Some(record) => {
+                            let record = try!(record);
+                            Song::parse(&record).unwrap_or_else(|e| parse::bug(e)).pos
+                        }
+                        None => die("no song found"),
+                    }
+                };
+
+                try!(conn.send(Command::Play { position: pos }));
+                try!(conn.recv());
+            }
+            (cmd @ "disable", Some(matches)) |
+            (cmd @ "enable", Some(matches)) |
+            (cmd @ "toggleoutput", Some(matches)) => {
+                let ids = try!(outputs(conn, matches.values_of("output").unwrap()));
+
+                let cmds = ids.into_iter()
+                              .map(|id| {
+                                  match cmd {
+                                      "disable" => Command::DisableOutput { id: id },
+                                      "enable" => Command::EnableOutput { id: id },
+                                      "toggleoutput" => Command::ToggleOutput { id: id },
+                                      _ => unreachable!(),
+                                  }
+                              })
+                              .collect::<Vec<_>>();
+
+                try!(conn.send_list(&cmds));
+                try!(conn.recv_list());
+            }
+            ("del", Some(matches)) => {
+                let mut ranges = matches.values_of("songpos")
+                                        .unwrap()
+                                        .map(|s| range(s, matches.usage()))
+                                        .collect::<Vec<_>>();
+
+                // merge overlapping ranges, so no song is deleted twice
+                ranges.sort_by(|a, b| a.start.cmp(&b.start));
+                let mut merged: Vec<Range> = vec![];
+                for range in ranges {
+                    match merged.last_mut() {
+                        Some(last) if range.start <= last.end.unwrap() => {
+                            last.end = Some(cmp::max(last.end.unwrap(), range.end.unwrap()));
+                            continue;
+                        }
+                        _ => {}
+                    }
+
+                    merged.push(range);
+                }
+
+                // delete from the end of the playlist, so the positions of the songs yet to be
+                // deleted don't change
+                let cmds = merged.into_iter()
+                                 .rev()
+                                 .map(|range| Command::Delete { range: range })
+                                 .collect::<Vec<_>>();
+
+                try!(conn.send_list(&cmds));
+                try!(conn.recv_list());
+            }
+            ("prio", Some(matches)) => {
+                let priority = matches.value_of("priority").unwrap();
+                let ranges = matches.values_of("songpos")
+                                    .unwrap()
+                                    .map(|s| range(s, matches.usage()))
+                                    .collect::<Vec<_>>();
+
+                try!(conn.send(Command::Prio {
+                    priority: priority.parse()
+                                      .unwrap_or_else(|_| invalid_value(priority, matches.usage())),
+                    ranges: &ranges,
+                }));
+                try!(conn.recv());
+            }
+            ("move", Some(matches)) => {
+                try!(conn.send(Command::Move {
+                    from: range(matches.value_of("from").unwrap(), matches.usage()),
+                    to: Position::Absolute(position(matches.value_of("to").unwrap(),
+                                                    matches.usage())),
+                }));
+                try!(conn.recv());
+            }
+            ("swap", Some(matches)) => {
+                try!(conn.send(Command::Swap {
+                    pos1: position(matches.value_of("pos1").unwrap(), matches.usage()),
+                    pos2: position(matches.value_of("pos2").unwrap(), matches.usage()),
+                }));
+                try!(conn.recv());
+            }
+            ("seek", Some(matches)) => {
+                let value = matches.value_of("time").unwrap();
+
+                let total = {
+                    try!(conn.send(Command::Status));
+                    let status = Status::parse(try!(conn.recv()))
+                                     .unwrap_or_else(|e| parse::bug(e));
+
+                    match status.extra {
+                        Some(ref extra) if status.state != State::Stop => {
+                            extra.duration
+                                 .or_else(|| extra.time.as_ref().map(|time| f64::from(time.total)))
+                        }
+                        _ => die("not currently playing"),
+                    }
+                };
+
+                let time = if value.ends_with('%') {
+                    match value[..value.len() - 1].parse::<f64>() {
+                        Ok(percent) if percent >= 0. && percent <= 100. => {
+                            match total {
+                                Some(total) => SeekTarget::Absolute(total * percent / 100.),
+                                None => die("the current song has no known duration"),
+                            }
+                        }
+                        _ => invalid_value(value, matches.usage()),
+                    }
+                } else if value.starts_with('+') {
+                    SeekTarget::Forward(seconds(&value[1..], matches.usage()))
+                } else if value.starts_with('-') {
+                    SeekTarget::Backward(seconds(&value[1..], matches.usage()))
+                } else {
+                    SeekTarget::Absolute(seconds(value, matches.usage()))
+                };
+
+                try!(conn.send(Command::SeekCur { time: time }));
+                try!(conn.recv());
+            }
+            ("crop", _) => {
+                let (pos, length) = {
+                    try!(conn.send(Command::Status));
+                    let status = Status::parse(try!(conn.recv()))
+                                     .unwrap_or_else(|e| parse::bug(e));
+
+                    match status.extra {
+                        Some(Extra { pos, .. }) if status.state != State::Stop => {
+                            (pos, status.playlist_length)
+                        }
+                        _ => die("you need to be playing to crop the playlist"),
+                    }
+                };
+
+                let mut cmds = vec![];
+                if pos + 1 < length {
+                    cmds.push(Command::Delete {
+                        range: Range {
+                            start: pos + 1,
+                            end: Some(length),
+                        },
+                    });
+                }
+                if pos > 0 {
+                    cmds.push(Command::Delete {
+                        range: Range {
+                            start: 0,
+                            end: Some(pos),
+                        },
+                    });
+                }
+
+                try!(conn.send_list(&cmds));
+                try!(conn.recv_list());
+            }
+            // Commands with a single required argument
+            ("sendmessage", Some(matches)) => {
+                try!(conn.send(Command::SendMessage {
+                    channel: matches.value_of("channel").unwrap(),
+                    text: matches.value_of("text").unwrap(),
+                }));
+                try!(conn.recv());
+            }
+            ("rm", Some(matches)) => {
+                try!(conn.send(Command::Rm { name: matches.value_of("name").unwrap() }));
+                try!(conn.recv());
+            }
+            ("save", Some(matches)) => {
+                try!(conn.send(Command::Save {
+                    name: matches.value_of("name").unwrap(),
+                    mode: None,
+                }));
                 try!(conn.recv());
             }
             ("volume", Some(matches)) => {
@@ -151,12 +595,7 @@ fn run() -> io::Result<()> {
             // Command::Play has a special argument restriction (> 0)
             ("play", Some(matches)) => {
                 try!(conn.send(Command::Play {
-                    position: matches.value_of("position").map(|s| {
-                        s.parse::<u32>()
-                         .ok()
-                         .and_then(|i| i.checked_sub(1))
-                         .unwrap_or_else(|| invalid_value(s, matches.usage()))
-                    }),
+                    position: matches.value_of("position").map(|s| position(s, matches.usage())),
                 }));
                 try!(conn.recv());
             }
@@ -175,11 +614,11 @@ fn run() -> io::Result<()> {
 
                 try!(conn.send(Command::ListAll { uri: matches.value_of("uri") }));
 
-                for line in try!(conn.recv()).lines() {
-                    const FILE: &'static str = "file: ";
+                for pair in conn.recv_pairs() {
+                    let (key, value) = try!(pair);
 
-                    if line.starts_with(FILE) {
-                        println!("{}", &line[FILE.len()..]);
+                    if key == "file" {
+                        println!("{}", value);
                     }
                 }
             }
@@ -187,31 +626,340 @@ fn run() -> io::Result<()> {
                 quiet = true;
 
                 try!(conn.send(Command::PlaylistInfo));
-                let mut text = try!(conn.recv());
 
-                if !text.trim().is_empty() {
-                    while let Some(end) = text.find("\nfile:") {
-                        let song = Song::parse(&text[..end]).unwrap_or_else(|e| parse::bug(e));
-                        println!("{} - {}", song.artist, song.title);
-                        text = &text[end + 1..];
+                for record in parse::records(try!(conn.recv())) {
+                    let song = Song::parse(record).unwrap_or_else(|e| parse::bug(e));
+                    println!("{}", pretty(&song));
+                }
+            }
+            (cmd @ "find", Some(matches)) |
+            (cmd @ "search", Some(matches)) => {
+                quiet = true;
+
+                let op = if cmd == "find" {
+                    Op::Eq
+                } else {
+                    Op::Contains
+                };
+                let filter = &filter(matches.values_of("query").unwrap().collect(),
+                                     op,
+                                     matches.usage());
+
+                try!(conn.send(if cmd == "find" {
+                    Command::Find {
+                        filter: filter,
+                        sort: None,
+                        window: None,
+                    }
+                } else {
+                    Command::Search {
+                        filter: filter,
+                        sort: None,
+                        window: None,
+                    }
+                }));
+
+                for record in conn.recv_records() {
+                    let record = try!(record);
+                    let song = Song::parse(&record).unwrap_or_else(|e| parse::bug(e));
+                    println!("{}", pretty(&song));
+                }
+            }
+            ("list", Some(matches)) => {
+                quiet = true;
+
+                let mut query = matches.values_of("query")
+                                       .map(|values| values.collect())
+                                       .unwrap_or_else(Vec::new);
+                let mut group = vec![];
+                while query.len() >= 2 && query[query.len() - 2] == "group" {
+                    group.insert(0, query.pop().unwrap());
+                    query.pop();
+                }
+                let filter = if query.is_empty() {
+                    None
+                } else {
+                    Some(filter(query, Op::Eq, matches.usage()))
+                };
+
+                try!(conn.send(Command::List {
+                    tag: matches.value_of("tag").unwrap(),
+                    filter: filter.as_ref(),
+                    group: &group,
+                }));
+
+                fn print(groups: &[Group], depth: usize) {
+                    for group in groups {
+                        println!("{:2$}{}", "", group.value, 2 * depth);
+                        print(&group.children, depth + 1);
+                    }
+                }
+
+                print(&parse::groups(try!(conn.recv())).unwrap_or_else(|e| parse::bug(e)),
+                      0);
+            }
+            ("ls", Some(matches)) => {
+                quiet = true;
+
+                try!(conn.send(Command::LsInfo { uri: matches.value_of("uri") }));
+
+                for record in conn.recv_records() {
+                    let record = try!(record);
+
+                    match Entry::parse(&record).unwrap_or_else(|e| parse::bug(e)) {
+                        Entry::Directory(directory) => println!("{}", directory.path),
+                        Entry::Playlist(playlist) => println!("{}", playlist.name),
+                        Entry::Song(song) => println!("{}", song.file),
+                    }
+                }
+            }
+            ("outputs", _) => {
+                quiet = true;
+
+                try!(conn.send(Command::Outputs));
+
+                for output in parse::outputs(try!(conn.recv())).unwrap_or_else(|e| parse::bug(e)) {
+                    println!("Output {} ({}) is {}",
+                             output.id + 1,
+                             output.name,
+                             if output.enabled {
+                                 "enabled"
+                             } else {
+                                 "disabled"
+                             });
+
+                    for (name, value) in &output.attributes {
+                        println!("\t{}={}", name, value);
+                    }
+                }
+            }
+            ("stats", _) => {
+                quiet = true;
+
+                try!(conn.send(Command::Stats));
+                let stats = Stats::parse(try!(conn.recv())).unwrap_or_else(|e| parse::bug(e));
+
+                if let (Some(artists), Some(albums), Some(songs)) =
+                       (stats.artists, stats.albums, stats.songs) {
+                    println!("Artists: {:>6}", artists);
+                    println!("Albums:  {:>6}", albums);
+                    println!("Songs:   {:>6}", songs);
+                    println!("");
+                }
+                println!("Play Time:    {}", duration(stats.playtime));
+                println!("Uptime:       {}", duration(stats.uptime));
+                if let Some(db_update) = stats.db_update {
+                    println!("DB Updated:   {}", date(db_update));
+                }
+                if let Some(db_playtime) = stats.db_playtime {
+                    println!("DB Play Time: {}", duration(db_playtime));
+                }
+            }
+            ("sticker", Some(matches)) => {
+                quiet = true;
+
+                let uri = matches.value_of("uri").unwrap();
+                let action = matches.value_of("action").unwrap();
+                let args = matches.values_of("args")
+                                  .map(|values| values.collect())
+                                  .unwrap_or_else(Vec::new);
+
+                let cmd = match (action, &args[..]) {
+                    ("delete", &[]) => {
+                        Command::StickerDelete {
+                            kind: "song",
+                            uri: uri,
+                            name: None,
+                        }
+                    }
+                    ("delete", &[name]) => {
+                        Command::StickerDelete {
+                            kind: "song",
+                            uri: uri,
+                            name: Some(name),
+                        }
+                    }
+                    ("find", &[name]) => {
+                        Command::StickerFind {
+                            kind: "song",
+                            uri: uri,
+                            name: name,
+                            filter: None,
+                        }
+                    }
+                    ("find", &[name, op, value]) => {
+                        Command::StickerFind {
+                            kind: "song",
+                            uri: uri,
+                            name: name,
+                            filter: Some(match op {
+                                "=" => StickerOp::Eq(value),
+                                "<" => StickerOp::Lt(value),
+                                ">" => StickerOp::Gt(value),
+                                _ => invalid_value(op, matches.usage()),
+                            }),
+                        }
+                    }
+                    ("get", &[name]) => {
+                        Command::StickerGet {
+                            kind: "song",
+                            uri: uri,
+                            name: name,
+                        }
+                    }
+                    ("list", &[]) => {
+                        Command::StickerList {
+                            kind: "song",
+                            uri: uri,
+                        }
+                    }
+                    ("set", &[name, value]) => {
+                        Command::StickerSet {
+                            kind: "song",
+                            uri: uri,
+                            name: name,
+                            value: value,
+                        }
+                    }
+                    _ => die(format!("wrong number of arguments for sticker {}", action)),
+                };
+
+                try!(conn.send(cmd));
+                let stickers = parse::stickers(try!(conn.recv())).unwrap_or_else(|e| parse::bug(e));
+
+                for sticker in stickers {
+                    match sticker.uri {
+                        Some(uri) => println!("{}: {}={}", uri, sticker.name, sticker.value),
+                        None => println!("{}={}", sticker.name, sticker.value),
                     }
+                }
+            }
+            ("channels", _) => {
+                quiet = true;
+
+                try!(conn.send(Command::Channels));
 
-                    let song = Song::parse(text).unwrap_or_else(|e| parse::bug(e));
-                    println!("{} - {}", song.artist, song.title);
+                for channel in parse::channels(try!(conn.recv()))
+                                   .unwrap_or_else(|e| parse::bug(e)) {
+                    println!("{}", channel);
                 }
+            }
+            (cmd @ "subscribe", Some(matches)) |
+            (cmd @ "waitmessage", Some(matches)) => {
+                quiet = true;
 
+                let channel = matches.value_of("channel").unwrap();
+
+                try!(conn.send(Command::Subscribe { channel: channel }));
+                try!(conn.recv());
+
+                // this connection is only subscribed to one channel, so every message comes from it
+                let mut events = conn_opt.take().unwrap().events(&[Subsystem::Message]);
+                while let Some(event) = events.next() {
+                    try!(event);
+
+                    let messages = try!(events.read_messages());
+                    for message in &messages {
+                        println!("{}", message.text);
+                    }
+
+                    if cmd == "waitmessage" && !messages.is_empty() {
+                        break;
+                    }
+                }
+            }
+            ("lsplaylists", _) => {
+                quiet = true;
+
+                try!(conn.send(Command::ListPlaylists));
+
+                for record in parse::records(try!(conn.recv())) {
+                    let playlist = Playlist::parse(record).unwrap_or_else(|e| parse::bug(e));
+                    println!("{}", playlist.name);
+                }
             }
             _ => {}
         }
     }
 
     if !quiet {
-        try!(status(try!(connect(conn_opt))));
+        try!(status(try!(connect(conn_opt, &host, port))));
     }
 
     Ok(())
 }
 
+/// Parses `[[HH:]MM:]SS` as a number of seconds, the seconds may be fractional
+fn seconds(value: &str, usage: &str) -> f64 {
+    let parts = value.split(':').collect::<Vec<_>>();
+
+    if parts.len() > 3 {
+        invalid_value(value, usage)
+    }
+
+    // only the leading component may be 60 or more
+    let mut minutes = 0u32;
+    for (i, part) in parts[..parts.len() - 1].iter().enumerate() {
+        minutes = match part.parse::<u32>() {
+            Ok(part) if i == 0 || part < 60 => {
+                match minutes.checked_mul(60).and_then(|minutes| minutes.checked_add(part)) {
+                    Some(minutes) => minutes,
+                    None => invalid_value(value, usage),
+                }
+            }
+            _ => invalid_value(value, usage),
+        };
+    }
+
+    match parts[parts.len() - 1].parse::<f64>() {
+        Ok(seconds) if seconds.is_finite() && seconds >= 0. &&
+                       (parts.len() == 1 || seconds < 60.) => {
+            60. * f64::from(minutes) + seconds
+        }
+        _ => invalid_value(value, usage),
+    }
+}
+
+/// Formats a number of seconds as `{days} days, {hours}:{minutes}:{seconds}`
+fn duration(seconds: u64) -> String {
+    format!("{} days, {}:{:02}:{:02}",
+            seconds / 86400,
+            seconds / 3600 % 24,
+            seconds / 60 % 60,
+            seconds % 60)
+}
+
+/// Formats a UNIX timestamp as `YYYY-MM-DD hh:mm:ss UTC`
+fn date(timestamp: u64) -> String {
+    // days since 0000-03-01, so leap days fall at the end of the year
+    let days = timestamp / 86400 + 719468;
+    let era = days / 146097;
+    let day_of_era = days % 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 -
+                       day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = era * 400 + year_of_era + if month <= 2 { 1 } else { 0 };
+
+    format!("{}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+            year,
+            month,
+            day,
+            timestamp / 3600 % 24,
+            timestamp / 60 % 60,
+            timestamp % 60)
+}
+
+/// Prints an error message and exits
+fn die<T>(msg: T) -> !
+    where T: fmt::Display
+{
+    writeln!(io::stderr(), "{} {}", Format::Error("error:"), msg).unwrap();
+    process::exit(1);
+}
+
 /// Encountered an invalid value, print an error message and exit
 fn invalid_value(value: &str, usage: &str) -> ! {
     println!("{} '{}' isn't a valid value\n\n{}\n\nPlease re-run with {} for more information",
@@ -222,18 +970,132 @@ fn invalid_value(value: &str, usage: &str) -> ! {
     process::exit(1);
 }
 
+/// Parses a song position as displayed by `playlist`, i.e. starting at 1
+fn position(value: &str, usage: &str) -> u32 {
+    value.parse::<u32>()
+         .ok()
+         .and_then(|i| i.checked_sub(1))
+         .unwrap_or_else(|| invalid_value(value, usage))
+}
+
+/// Parses a song position, `N`, or an inclusive range of song positions, `N-M`
+fn range(value: &str, usage: &str) -> Range {
+    let parts = &mut value.splitn(2, '-');
+    let start = position(parts.next().unwrap(), usage);
+    let end = parts.next().map(|end| position(end, usage) + 1).unwrap_or(start + 1);
+
+    if end <= start {
+        invalid_value(value, usage)
+    }
+
+    Range {
+        start: start,
+        end: Some(end),
+    }
+}
+
+/// Builds a filter from `{tag} {value}` pairs, where the value of each tag is compared using `op`,
+/// or from a single filter expression in MPD syntax
+fn filter<'a>(args: Vec<&'a str>, op: Op, usage: &str) -> Filter<'a> {
+    if args.len() == 1 && args[0].starts_with('(') {
+        return Filter::Raw(args[0]);
+    }
+
+    if args.len() % 2 != 0 {
+        invalid_value(args[args.len() - 1], usage)
+    }
+
+    Filter::And(args.chunks(2)
+                    .map(|pair| {
+                        Filter::Tag {
+                            tag: pair[0],
+                            op: op,
+                            value: pair[1],
+                        }
+                    })
+                    .collect())
+}
+
 /// Connects to MPD if not yet connected, otherwise returns the current connection
-fn connect(conn_opt: &mut Option<Connection>) -> io::Result<&mut Connection> {
+///
+/// `host` may be prefixed with a password, as in `password@host`. On Unix, a `host` that is an
+/// absolute path is a Unix domain socket, in that case `port` is ignored.
+fn connect<'a>(conn_opt: &'a mut Option<Connection>,
+               host: &str,
+               port: u16)
+               -> Result<&'a mut Connection, Error> {
     Ok(if let Some(ref mut conn) = *conn_opt {
         conn
     } else {
-        *conn_opt = Some(try!(mpd::connect("localhost:6600")));
+        let (password, host) = match host.find('@') {
+            // `{password}@{host}`, a leading `@` is part of the host name
+            Some(at) if at > 0 => (Some(&host[..at]), &host[at + 1..]),
+            _ => (None, host),
+        };
+
+        #[cfg(unix)]
+        let conn = if host.starts_with('/') {
+            mpd::connect_unix(host)
+        } else {
+            mpd::connect((host, port))
+        };
+        #[cfg(not(unix))]
+        let conn = mpd::connect((host, port));
+        let mut conn = try!(conn);
+
+        if let Some(password) = password {
+            try!(conn.password(password));
+        }
+
+        *conn_opt = Some(conn);
         conn_opt.as_mut().unwrap()
     })
 }
 
+/// Formats `song` like upstream mpc does: `[{name}: ][{artist} - ]{title}`
+///
+/// If the song has no title, it's displayed as `{name}` (streams) or `[{artist} - ]{file}`
+fn pretty<'a>(song: &Song<'a>) -> Cow<'a, str> {
+    let title = match (song.name, song.title) {
+        (Some(name), None) => return Cow::from(name),
+        (_, Some(title)) => title,
+        (None, None) => song.file,
+    };
+
+    let artist_title = match song.artist.first() {
+        Some(artist) => Cow::from(format!("{} - {}", artist, title)),
+        None => Cow::from(title),
+    };
+
+    match (song.name, song.title) {
+        (Some(name), Some(_)) => Cow::from(format!("{}: {}", name, artist_title)),
+        _ => artist_title,
+    }
+}
+
+/// Maps output numbers (1-based, as printed by `mpc outputs`) or names to output ids
+fn outputs<'a, I>(conn: &mut Connection, values: I) -> Result<Vec<u32>, Error>
+    where I: Iterator<Item = &'a str>
+{
+    try!(conn.send(Command::Outputs));
+    let outputs = parse::outputs(try!(conn.recv())).unwrap_or_else(|e| parse::bug(e));
+
+    Ok(values.map(|value| {
+                 match value.parse::<u32>() {
+                     Ok(n) if outputs.iter().any(|output| output.id + 1 == n) => n - 1,
+                     _ => {
+                         match outputs.iter().find(|output| output.name == value) {
+                             Some(output) => output.id,
+                             None => die(format!("unknown output: {}", value)),
+                         }
+                     }
+                 }
+             })
+             .collect())
+}
+
 /// Prints status information
-fn status(conn: &mut Connection) -> io::Result<()> {
+fn status(conn: &mut Connection) -> Result<(), Error> {
     fn onoff(on: bool) -> &'static str {
         if on {
             "on "
@@ -242,8 +1104,9 @@ fn status(conn: &mut Connection) -> io::Result<()> {
         }
     }
 
-    try!(conn.send(Command::Status));
-    let status = Status::parse(try!(conn.recv())).unwrap_or_else(|e| parse::bug(e));
+    try!(conn.send_list(&[Command::Status, Command::CurrentSong]));
+    let outputs = try!(conn.recv_list());
+    let status = Status::parse(outputs[0]).unwrap_or_else(|e| parse::bug(e));
 
     let state = match status.state {
         State::Pause => Some("paused"),
@@ -252,11 +1115,9 @@ fn status(conn: &mut Connection) -> io::Result<()> {
     };
 
     if let (Some(state), Some(Extra { pos, time: Some(ref time), .. })) = (state, status.extra) {
-        try!(conn.send(Command::CurrentSong));
+        let song = Song::parse(outputs[1]).unwrap_or_else(|e| parse::bug(e));
 
-        let song = Song::parse(try!(conn.recv())).unwrap_or_else(|e| parse::bug(e));
-
-        println!("{} - {}", song.artist, song.title);
+        println!("{}", pretty(&song));
         println!("[{}] #{}/{}   {}:{:02}/{}:{:02} ({}%)",
                  state,
                  pos + 1,
@@ -265,7 +1126,8 @@ fn status(conn: &mut Connection) -> io::Result<()> {
                  time.elapsed % 60,
                  time.total / 60,
                  time.total % 60,
-                 100 * time.elapsed / time.total);
+                 // streams have no duration
+                 (100 * time.elapsed).checked_div(time.total).unwrap_or(0));
     }
 
     if let Some(id) = status.updating_db {
@@ -279,5 +1141,9 @@ fn status(conn: &mut Connection) -> io::Result<()> {
              onoff(status.single),
              onoff(status.consume));
 
+    if let Some(error) = status.error {
+        println!("ERROR: {}", error);
+    }
+
     Ok(())
 }
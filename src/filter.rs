@@ -0,0 +1,175 @@
+//! Filter expressions, as used by `Find`, `Search` and friends
+//!
+//! These render to the filter syntax introduced in MPD 0.21, e.g. `((Artist == 'x') AND (Album
+//! contains 'y'))`
+
+use std::fmt;
+
+use Error;
+
+/// Comparison between the value of a tag and a string
+#[derive(Clone, Copy)]
+pub enum Op {
+    /// `==`: exact match
+    Eq,
+    /// `!=`: anything but an exact match
+    Ne,
+    /// `contains`: substring match
+    Contains,
+    /// `starts_with`: prefix match
+    StartsWith,
+    /// `=~`: Perl-compatible regular expression match
+    Regex,
+    /// `!~`: negated regular expression match
+    NotRegex,
+}
+
+impl Op {
+    fn str(&self) -> &'static str {
+        use self::Op::*;
+
+        match *self {
+            Eq => "==",
+            Ne => "!=",
+            Contains => "contains",
+            StartsWith => "starts_with",
+            Regex => "=~",
+            NotRegex => "!~",
+        }
+    }
+}
+
+/// A filter expression
+pub enum Filter<'a> {
+    /// Songs that were added to the database after `since`
+    AddedSince {
+        /// UNIX timestamp or ISO 8601 date
+        since: &'a str,
+    },
+    /// Songs that match all the filters, which must not be empty
+    And(Vec<Filter<'a>>),
+    /// Songs with the given audio format
+    AudioFormat {
+        /// `{sample_rate}:{bits}:{channels}`
+        format: &'a str,
+        /// If `true`, `format` is a mask where any component can be `*`, e.g. `*:16:*`
+        mask: bool,
+    },
+    /// Songs within the directory `uri`
+    Base {
+        /// Directory, relative to the music directory
+        uri: &'a str,
+    },
+    /// Songs whose files were modified after `since`
+    ModifiedSince {
+        /// UNIX timestamp or ISO 8601 date
+        since: &'a str,
+    },
+    /// Songs that don't match the filter
+    Not(Box<Filter<'a>>),
+    /// A filter expression in MPD syntax, which is sent as is
+    Raw(&'a str),
+    /// Songs whose `tag` compares to `value` according to `op`
+    Tag {
+        /// A tag name like `Artist`, or one of the special tags `any` and `file`. Only letters,
+        /// digits, `_` and `-` are allowed
+        tag: &'a str,
+        /// Comparison operator
+        op: Op,
+        /// Value to compare against
+        value: &'a str,
+    },
+}
+
+impl<'a> Filter<'a> {
+    /// Rejects the filters that can't be rendered as a valid expression: an empty `And` and tag
+    /// names that would change the structure of the expression
+    pub(crate) fn check(&self) -> Result<(), Error> {
+        use self::Filter::*;
+
+        match *self {
+            And(ref filters) if filters.is_empty() => {
+                Err(Error::InvalidArgument(String::from("empty AND filter")))
+            }
+            And(ref filters) => {
+                for filter in filters {
+                    try!(filter.check());
+                }
+
+                Ok(())
+            }
+            Not(ref filter) => filter.check(),
+            Tag { tag, .. } => check_tag(tag),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Rejects tag names that are empty or contain anything other than ASCII alphanumerics, `_` and
+/// `-`
+pub(crate) fn check_tag(tag: &str) -> Result<(), Error> {
+    if tag.is_empty() || !tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        Err(Error::InvalidArgument(tag.to_owned()))
+    } else {
+        Ok(())
+    }
+}
+
+/// Writes `value` as a single quoted string
+fn quote(value: &str, f: &mut fmt::Formatter) -> fmt::Result {
+    try!(f.write_str("'"));
+    for c in value.chars() {
+        if c == '\'' || c == '\\' {
+            try!(f.write_str("\\"));
+        }
+        try!(write!(f, "{}", c));
+    }
+    f.write_str("'")
+}
+
+impl<'a> fmt::Display for Filter<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::Filter::*;
+
+        match *self {
+            AddedSince { since } => {
+                try!(f.write_str("(added-since "));
+                try!(quote(since, f));
+                f.write_str(")")
+            }
+            And(ref filters) if filters.len() == 1 => filters[0].fmt(f),
+            And(ref filters) => {
+                try!(f.write_str("("));
+                for (i, filter) in filters.iter().enumerate() {
+                    if i != 0 {
+                        try!(f.write_str(" AND "));
+                    }
+                    try!(filter.fmt(f));
+                }
+                f.write_str(")")
+            }
+            AudioFormat { format, mask } => {
+                try!(write!(f, "(AudioFormat {} ", if mask { "=~" } else { "==" }));
+                try!(quote(format, f));
+                f.write_str(")")
+            }
+            Base { uri } => {
+                try!(f.write_str("(base "));
+                try!(quote(uri, f));
+                f.write_str(")")
+            }
+            ModifiedSince { since } => {
+                try!(f.write_str("(modified-since "));
+                try!(quote(since, f));
+                f.write_str(")")
+            }
+            Not(ref filter) => write!(f, "(!{})", filter),
+            Raw(expr) => f.write_str(expr),
+            Tag { tag, op, value } => {
+                try!(write!(f, "({} {} ", tag, op.str()));
+                try!(quote(value, f));
+                f.write_str(")")
+            }
+        }
+    }
+}
@@ -8,13 +8,122 @@
 extern crate bufstream;
 
 use std::borrow::Cow;
-use std::io::{self, BufRead, Write};
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{self, BufRead, Read, Write};
+use std::mem;
 use std::net::{TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+#[cfg(unix)]
+use std::path::Path;
+use std::{error, fmt};
 
 use bufstream::BufStream;
 
+pub mod filter;
 pub mod parse;
 
+use filter::Filter;
+
+/// Error code of a failed command, as reported by MPD
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AckCode {
+    /// `ACK_ERROR_NOT_LIST`
+    NotList,
+    /// `ACK_ERROR_ARG`: invalid argument
+    Arg,
+    /// `ACK_ERROR_PASSWORD`: wrong password
+    Password,
+    /// `ACK_ERROR_PERMISSION`: not permitted to run the command
+    Permission,
+    /// `ACK_ERROR_UNKNOWN`: unknown command
+    Unknown,
+    /// `ACK_ERROR_NO_EXIST`: the requested object (file, song, playlist, etc) doesn't exist
+    NoExist,
+    /// `ACK_ERROR_PLAYLIST_MAX`: the playlist is full
+    PlaylistMax,
+    /// `ACK_ERROR_SYSTEM`: system error (e.g. I/O error on the server side)
+    System,
+    /// `ACK_ERROR_PLAYLIST_LOAD`: couldn't load a playlist
+    PlaylistLoad,
+    /// `ACK_ERROR_UPDATE_ALREADY`: a database update is already running
+    UpdateAlready,
+    /// `ACK_ERROR_PLAYER_SYNC`: player state changed while running the command
+    PlayerSync,
+    /// `ACK_ERROR_EXIST`: the object to be created already exists
+    Exist,
+    /// An error code unknown to this library
+    Other(u32),
+}
+
+/// A failed command
+///
+/// MPD reports these as `ACK [{code}@{index}] {{{command}}} {message}`
+#[derive(Debug)]
+pub struct Ack {
+    _0: (),
+    /// Error code
+    pub code: AckCode,
+    /// Position of the failed command in the command list, `0` if not in a command list
+    pub index: u32,
+    /// Name of the failed command, may be empty
+    pub command: String,
+    /// Error message
+    pub message: String,
+}
+
+impl fmt::Display for Ack {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.command.is_empty() {
+            f.write_str(&self.message)
+        } else {
+            write!(f, "{}: {}", self.command, self.message)
+        }
+    }
+}
+
+impl error::Error for Ack {}
+
+/// Library error
+#[derive(Debug)]
+pub enum Error {
+    /// MPD failed to execute a command
+    Ack(Ack),
+    /// A command argument contains characters that can't be sent to MPD (e.g. a newline)
+    InvalidArgument(String),
+    /// I/O error
+    Io(io::Error),
+    /// Couldn't parse MPD's reply
+    Parse(String),
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+impl<'a> From<parse::Error<'a>> for Error {
+    fn from(e: parse::Error<'a>) -> Error {
+        Error::Parse(e.to_string())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Ack(ref ack) => ack.fmt(f),
+            Error::InvalidArgument(ref arg) => {
+                write!(f, "invalid argument {:?}: contains control characters", arg)
+            }
+            Error::Io(ref e) => e.fmt(f),
+            Error::Parse(ref msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
 /// MPD status
 #[derive(PartialEq)]
 pub enum State {
@@ -26,13 +135,147 @@ pub enum State {
     Stop,
 }
 
-#[allow(missing_docs)]
 /// Song information
+///
+/// Tags that may appear more than once in a song are collected in a `Vec`, other tags keep their
+/// first value.
 pub struct Song<'a> {
-    // TODO parse other fields
     _0: (),
-    pub artist: &'a str,
-    pub title: &'a str,
+    /// Album name
+    pub album: Option<&'a str>,
+    /// Artists of the album
+    pub album_artist: Vec<&'a str>,
+    /// Artists
+    pub artist: Vec<&'a str>,
+    /// Composers
+    pub composer: Vec<&'a str>,
+    /// Release date, usually a 4-digit year
+    pub date: Option<&'a str>,
+    /// Disc number in a multi-disc album, may be formatted as `{disc}/{total}`
+    pub disc: Option<&'a str>,
+    /// Duration in seconds, with higher precision
+    pub duration: Option<f64>,
+    /// URI of the song, relative to the music directory
+    pub file: &'a str,
+    /// Genres
+    pub genre: Vec<&'a str>,
+    /// Id of the song in the playlist
+    pub id: Option<u32>,
+    /// Time of the last modification of the file, in ISO 8601 format
+    pub last_modified: Option<&'a str>,
+    /// MusicBrainz album id
+    pub musicbrainz_album_id: Option<&'a str>,
+    /// MusicBrainz album artist id
+    pub musicbrainz_album_artist_id: Option<&'a str>,
+    /// MusicBrainz artist id
+    pub musicbrainz_artist_id: Option<&'a str>,
+    /// MusicBrainz release track id
+    pub musicbrainz_release_track_id: Option<&'a str>,
+    /// MusicBrainz recording id
+    pub musicbrainz_track_id: Option<&'a str>,
+    /// MusicBrainz work id
+    pub musicbrainz_work_id: Option<&'a str>,
+    /// Name of the stream, for radio streams
+    pub name: Option<&'a str>,
+    /// Tags not covered by the other fields, e.g. `Performer`, `Comment` or `Format`
+    pub other: BTreeMap<&'a str, Vec<&'a str>>,
+    /// Position of the song in the playlist
+    pub pos: Option<u32>,
+    /// Priority of the song in the playlist
+    pub prio: Option<u8>,
+    /// Size of the file in bytes, only reported by `ListFiles`
+    pub size: Option<u64>,
+    /// Duration in seconds
+    pub time: Option<u32>,
+    /// Song title
+    pub title: Option<&'a str>,
+    /// Track number within the album, may be formatted as `{track}/{total}`
+    pub track: Option<&'a str>,
+}
+
+/// A directory of the music database
+pub struct Directory<'a> {
+    _0: (),
+    /// Time of the last modification of the directory, in ISO 8601 format
+    pub last_modified: Option<&'a str>,
+    /// Path of the directory, relative to the music directory
+    pub path: &'a str,
+}
+
+/// A sticker: a name-value pair attached to an object of the database
+pub struct Sticker<'a> {
+    _0: (),
+    /// Name of the sticker
+    pub name: &'a str,
+    /// URI of the object the sticker is attached to. Only reported by `StickerFind`
+    pub uri: Option<&'a str>,
+    /// Value of the sticker
+    pub value: &'a str,
+}
+
+/// A stored playlist
+pub struct Playlist<'a> {
+    _0: (),
+    /// Time of the last modification of the playlist, in ISO 8601 format
+    pub last_modified: Option<&'a str>,
+    /// Name of the playlist
+    pub name: &'a str,
+}
+
+/// An entry of the output of commands like `ListAll` or `PlaylistInfo`
+pub enum Entry<'a> {
+    /// A directory
+    Directory(Directory<'a>),
+    /// A stored playlist
+    Playlist(Playlist<'a>),
+    /// A song
+    Song(Song<'a>),
+}
+
+/// A value of the output of the `List` command, with the values grouped under it
+pub struct Group<'a> {
+    _0: (),
+    /// Values of the next tag down the grouping, empty for the listed tag itself
+    pub children: Vec<Group<'a>>,
+    /// Tag name, as reported by MPD, e.g. `AlbumArtist`
+    pub tag: &'a str,
+    /// Tag value, empty for songs that lack the tag
+    pub value: &'a str,
+}
+
+/// Song count and total playtime of the songs that match the filter of the `Count` command
+pub struct Count<'a> {
+    _0: (),
+    /// Value of the group tag, `None` if the songs weren't grouped
+    pub group: Option<&'a str>,
+    /// Total playtime in seconds
+    pub playtime: u32,
+    /// Number of songs
+    pub songs: u32,
+}
+
+/// A message received on a channel, see `Command::ReadMessages`
+pub struct Message<'a> {
+    _0: (),
+    /// Channel the message was sent to
+    pub channel: &'a str,
+    /// Text of the message
+    pub text: &'a str,
+}
+
+/// An audio output
+pub struct Output<'a> {
+    _0: (),
+    /// Runtime attributes of the output plugin, e.g. `dop` for ALSA outputs
+    pub attributes: BTreeMap<&'a str, &'a str>,
+    /// Whether the output is enabled
+    pub enabled: bool,
+    /// Output id
+    pub id: u32,
+    /// Name of the output, as set in the MPD configuration
+    pub name: &'a str,
+    /// Output plugin, e.g. `alsa`, `httpd` or `pipe`. Only reported by MPD 0.21 and newer
+    pub plugin: Option<&'a str>,
 }
 
 /// Elapsed and total time
@@ -44,11 +287,41 @@ pub struct Time {
     pub total: u32,
 }
 
+/// Sample format of an `AudioFormat`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SampleFormat {
+    /// Integer samples of the given bit depth
+    Bits(u8),
+    /// Direct Stream Digital
+    Dsd,
+    /// 32-bit floating point samples
+    Float,
+}
+
+/// Audio format of the decoded stream
+pub struct AudioFormat {
+    _0: (),
+    /// Number of channels
+    pub channels: u8,
+    /// Sample format
+    pub format: SampleFormat,
+    /// Sample rate in Hz. For DSD, this is the bit rate divided by 8
+    pub sample_rate: u32,
+}
+
 /// Extra information about currently playing song
 pub struct Extra {
     _0: (),
+    /// Audio format of the decoded song
+    pub audio: Option<AudioFormat>,
+    /// Instantaneous bit rate in kbps
+    pub bitrate: Option<u32>,
+    /// Duration of the song in seconds, with higher precision
+    pub duration: Option<f64>,
     /// Elapsed time, with higher precision
     pub elapsed: Option<f64>,
+    /// Song id
+    pub id: Option<u32>,
     /// Song position
     pub pos: u32,
     /// Elapsed/total time
@@ -56,15 +329,30 @@ pub struct Extra {
 }
 
 /// MPD status
-pub struct Status {
-    // TODO parse other fields
+pub struct Status<'a> {
     _0: (),
     /// State of the consume mode
     pub consume: bool,
+    /// Error message of the last playback error, e.g. a missing file
+    pub error: Option<&'a str>,
     /// Extra information, available only when a song being played
     pub extra: Option<Extra>,
+    /// Name of the last stored playlist loaded into the current playlist
+    pub last_loaded_playlist: Option<&'a str>,
+    /// MixRamp threshold in dB
+    pub mixramp_db: Option<f64>,
+    /// MixRamp delay in seconds. `None` indicates that MixRamp is disabled
+    pub mixramp_delay: Option<f64>,
+    /// Position of the next song to be played
+    pub next_song: Option<u32>,
+    /// Id of the next song to be played
+    pub next_song_id: Option<u32>,
+    /// Name of the partition this client is using
+    pub partition: Option<&'a str>,
     /// Length of the playlist
     pub playlist_length: u32,
+    /// Version of the playlist, incremented each time the playlist is modified
+    pub playlist_version: u32,
     /// State of the random mode
     pub random: bool,
     /// State of the repeat mode
@@ -77,6 +365,29 @@ pub struct Status {
     pub updating_db: Option<u32>,
     /// Volume level. `None` indicates that MPD can't control the volume level
     pub volume: Option<u8>,
+    /// Crossfade in seconds
+    pub xfade: Option<u32>,
+}
+
+/// Statistics of the server and its database
+///
+/// The database statistics are `None` if MPD runs without a database
+pub struct Stats {
+    _0: (),
+    /// Number of albums in the database
+    pub albums: Option<u32>,
+    /// Number of artists in the database
+    pub artists: Option<u32>,
+    /// Total duration of the songs in the database, in seconds
+    pub db_playtime: Option<u64>,
+    /// Time of the last database update, as a UNIX timestamp
+    pub db_update: Option<u64>,
+    /// Time spent playing, in seconds
+    pub playtime: u64,
+    /// Number of songs in the database
+    pub songs: Option<u32>,
+    /// Time since the server was started, in seconds
+    pub uptime: u64,
 }
 
 #[allow(missing_docs)]
@@ -104,6 +415,189 @@ impl Mode {
     }
 }
 
+/// How `Save` handles an existing stored playlist
+pub enum SaveMode {
+    /// Fails if the playlist already exists
+    Create,
+    /// Appends the current playlist to the stored playlist
+    Append,
+    /// Replaces the stored playlist with the current playlist
+    Replace,
+}
+
+impl SaveMode {
+    fn str(&self) -> &'static str {
+        match *self {
+            SaveMode::Create => "create",
+            SaveMode::Append => "append",
+            SaveMode::Replace => "replace",
+        }
+    }
+}
+
+/// A range of positions in the playlist
+#[derive(Clone, Copy)]
+pub struct Range {
+    /// First position of the range
+    pub start: u32,
+    /// Position after the last one of the range. If `None`, the range extends to the end of the
+    /// playlist
+    pub end: Option<u32>,
+}
+
+impl From<u32> for Range {
+    /// A range that only contains `position`
+    fn from(position: u32) -> Range {
+        Range {
+            start: position,
+            // the range of the last possible position extends to the end of the playlist
+            end: position.checked_add(1),
+        }
+    }
+}
+
+impl Range {
+    fn str(&self) -> String {
+        match self.end {
+            None => format!("{}:", self.start),
+            // some commands only accept a single position on older versions of MPD
+            Some(end) if self.start.checked_add(1) == Some(end) => self.start.to_string(),
+            Some(end) => format!("{}:{}", self.start, end),
+        }
+    }
+}
+
+/// Sort order of the results of `Find` and `Search`
+#[derive(Clone, Copy)]
+pub struct Sort<'a> {
+    /// Tag to sort by, e.g. `Artist`, or `Last-Modified`
+    pub tag: &'a str,
+    /// If `true`, sorts in descending order
+    pub descending: bool,
+}
+
+/// A position in the playlist
+#[derive(Clone, Copy)]
+pub enum Position {
+    /// Absolute position
+    Absolute(u32),
+    /// `n` songs after the current song, `After(0)` is right after it
+    After(u32),
+    /// `n` songs before the current song, `Before(0)` is right before it
+    Before(u32),
+}
+
+impl Position {
+    fn str(&self) -> String {
+        match *self {
+            Position::Absolute(pos) => pos.to_string(),
+            Position::After(n) => format!("+{}", n),
+            Position::Before(n) => format!("-{}", n),
+        }
+    }
+}
+
+/// Target of the `SeekCur` command
+#[derive(Clone, Copy)]
+pub enum SeekTarget {
+    /// Absolute time in seconds
+    Absolute(f64),
+    /// Seconds forward from the current time
+    Forward(f64),
+    /// Seconds backward from the current time
+    Backward(f64),
+}
+
+impl SeekTarget {
+    fn str(&self) -> Result<String, Error> {
+        Ok(match *self {
+            SeekTarget::Absolute(time) => try!(seconds(time)),
+            SeekTarget::Forward(time) => format!("+{}", try!(seconds(time))),
+            SeekTarget::Backward(time) => format!("-{}", try!(seconds(time))),
+        })
+    }
+}
+
+/// Comparison between the value of a sticker and a string, used by `StickerFind`
+#[derive(Clone, Copy)]
+pub enum StickerOp<'a> {
+    /// Value is equal to the string
+    Eq(&'a str),
+    /// Value sorts before the string. Not supported by older servers
+    Lt(&'a str),
+    /// Value sorts after the string. Not supported by older servers
+    Gt(&'a str),
+}
+
+impl<'a> StickerOp<'a> {
+    fn str(&self) -> Result<String, Error> {
+        let (op, value) = match *self {
+            StickerOp::Eq(value) => ("=", value),
+            StickerOp::Lt(value) => ("<", value),
+            StickerOp::Gt(value) => (">", value),
+        };
+
+        Ok(format!("{} {}", op, try!(quote(value))))
+    }
+}
+
+/// A MPD subsystem, as reported by the `Idle` command
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Subsystem {
+    /// The song database has been modified after an update
+    Database,
+    /// A database update has started or finished
+    Update,
+    /// A stored playlist has been modified, renamed, created or deleted
+    StoredPlaylist,
+    /// The current playlist has been modified
+    Playlist,
+    /// The player has been started, stopped or seeked
+    Player,
+    /// The volume has been changed
+    Mixer,
+    /// An audio output has been added, removed or modified
+    Output,
+    /// Options like repeat, random, crossfade or replay gain have changed
+    Options,
+    /// A partition was added, removed or changed
+    Partition,
+    /// The sticker database has been modified
+    Sticker,
+    /// A client has subscribed to or unsubscribed from a channel
+    Subscription,
+    /// A message was received on a channel this client is subscribed to, see
+    /// `Events::read_messages`
+    Message,
+    /// A neighbor was found or lost
+    Neighbor,
+    /// The mount list has changed
+    Mount,
+}
+
+impl Subsystem {
+    fn str(&self) -> &'static str {
+        use self::Subsystem::*;
+
+        match *self {
+            Database => "database",
+            Update => "update",
+            StoredPlaylist => "stored_playlist",
+            Playlist => "playlist",
+            Player => "player",
+            Mixer => "mixer",
+            Output => "output",
+            Options => "options",
+            Partition => "partition",
+            Sticker => "sticker",
+            Subscription => "subscription",
+            Message => "message",
+            Neighbor => "neighbor",
+            Mount => "mount",
+        }
+    }
+}
+
 /// A MPD command
 pub enum Command<'a> {
     /// Adds the file `uri` to the playlist (directories are added recursively)
@@ -111,31 +605,301 @@ pub enum Command<'a> {
         /// If `None`, adds the whole database
         uri: &'a str,
     },
+    /// Adds the song `uri` to the playlist and returns its id, see `parse::id`
+    AddId {
+        /// URI of a song, directories can't be added with this command
+        uri: &'a str,
+        /// If `None`, appends the song to the playlist
+        position: Option<Position>,
+    },
+    /// Lists the channels with at least one subscribed client, see `parse::channels`
+    Channels,
     /// Clears the current playlist
     Clear,
     /// Displays the song info of the current song
     CurrentSong,
+    /// Disables the output `id`
+    DisableOutput {
+        /// Output id
+        id: u32,
+    },
+    /// Counts the songs that match `filter` and their total playtime, see `parse::counts`
+    Count {
+        /// If `None`, counts every song in the database
+        filter: Option<&'a Filter<'a>>,
+        /// If `Some`, counts the songs per value of this tag
+        group: Option<&'a str>,
+    },
+    /// Deletes the songs in `range` from the playlist
+    Delete {
+        /// Use `Range::from(position)` to delete a single song
+        range: Range,
+    },
+    /// Deletes the song with id `id` from the playlist
+    DeleteId {
+        /// Song id
+        id: u32,
+    },
+    /// Enables the output `id`
+    EnableOutput {
+        /// Output id
+        id: u32,
+    },
+    /// Searches the database for songs that match `filter` (case sensitive)
+    Find {
+        /// Filter expression
+        filter: &'a Filter<'a>,
+        /// If `None`, the results are not sorted
+        sort: Option<Sort<'a>>,
+        /// If `Some`, only returns the results in this range
+        window: Option<Range>,
+    },
+    /// Adds the songs that match `filter` (case sensitive) to the playlist
+    FindAdd {
+        /// Filter expression
+        filter: &'a Filter<'a>,
+        /// If `None`, the songs are added in database order
+        sort: Option<Sort<'a>>,
+        /// If `Some`, only adds the songs in this range of the results
+        window: Option<Range>,
+    },
+    /// Waits until there is a noteworthy change in one or more of MPD's subsystems
+    ///
+    /// The reply lists the changed subsystems, see `parse::changed`
+    Idle {
+        /// Only wait for changes in these subsystems. If empty, waits for changes in any subsystem
+        subsystems: &'a [Subsystem],
+    },
+    /// Lists the unique values of `tag` among the songs that match `filter`, see `parse::groups`
+    List {
+        /// Tag to list, e.g. `Album`
+        tag: &'a str,
+        /// If `None`, lists the values of the whole database
+        filter: Option<&'a Filter<'a>>,
+        /// Tags to group the values by, the outermost group first
+        group: &'a [&'a str],
+    },
     /// Lists all songs and directories in `uri`
     ListAll {
         /// If `None`, list everything in the database
         uri: Option<&'a str>,
     },
+    /// Lists all songs, with metadata, and directories in `uri`, see `parse::entries`
+    ListAllInfo {
+        /// If `None`, lists everything in the database
+        uri: Option<&'a str>,
+    },
+    /// Lists the files and directories in `uri`, including files that aren't songs, see
+    /// `parse::entries`
+    ListFiles {
+        /// If `None`, lists the root of the music directory
+        uri: Option<&'a str>,
+    },
+    /// Lists the songs in the stored playlist `name`
+    ListPlaylist {
+        /// Name of the stored playlist
+        name: &'a str,
+    },
+    /// Lists the songs, with metadata, in the stored playlist `name`
+    ListPlaylistInfo {
+        /// Name of the stored playlist
+        name: &'a str,
+    },
+    /// Lists the stored playlists, see `parse::entries`
+    ListPlaylists,
+    /// Lists the contents of the directory `uri`, one level deep, see `parse::entries`
+    LsInfo {
+        /// If `None`, lists the root of the music directory
+        uri: Option<&'a str>,
+    },
+    /// Loads the stored playlist `name` into the playlist
+    Load {
+        /// Name of the stored playlist
+        name: &'a str,
+        /// If `Some`, only loads the songs in this range of the stored playlist
+        range: Option<Range>,
+        /// If `Some`, inserts the songs at this position rather than appending them
+        position: Option<Position>,
+    },
+    /// Moves the songs in `from` to position `to` in the playlist
+    Move {
+        /// Songs to move
+        from: Range,
+        /// New position of the first song of the range
+        to: Position,
+    },
+    /// Moves the song with id `id` to position `to` in the playlist
+    MoveId {
+        /// Song id
+        id: u32,
+        /// New position of the song
+        to: Position,
+    },
     /// Plays next song in the playlist
     Next,
+    /// Cancels a pending `Idle`, which then returns immediately
+    NoIdle,
     /// Toggles pause/resumes playing
     Pause {
         /// `true`: pauses, `false`: resume playing
         state: bool,
     },
+    /// Sets the runtime attribute `name` of the output `id` to `value`
+    OutputSet {
+        /// Output id
+        id: u32,
+        /// Attribute name
+        name: &'a str,
+        /// Attribute value
+        value: &'a str,
+    },
+    /// Lists the audio outputs, see `parse::outputs`
+    Outputs,
+    /// Authenticates with `password`
+    Password {
+        /// Plain text password
+        password: &'a str,
+    },
     /// Begins playing the playlist at song `position`
     Play {
         /// if `None`, resumes playing the current song
         position: Option<u32>,
     },
+    /// Adds `uri` to the stored playlist `name`, which is created if it doesn't exist
+    PlaylistAdd {
+        /// Name of the stored playlist
+        name: &'a str,
+        /// URI of the song or directory to add
+        uri: &'a str,
+        /// If `None`, appends to the stored playlist
+        position: Option<u32>,
+    },
+    /// Clears the stored playlist `name`
+    PlaylistClear {
+        /// Name of the stored playlist
+        name: &'a str,
+    },
+    /// Deletes the songs in `range` from the stored playlist `name`
+    PlaylistDelete {
+        /// Name of the stored playlist
+        name: &'a str,
+        /// Use `Range::from(position)` to delete a single song
+        range: Range,
+    },
     /// Displays a list of all songs in the playlist
     PlaylistInfo,
+    /// Searches the playlist for songs that match `filter` (case insensitive)
+    PlaylistSearch {
+        /// Filter expression
+        filter: &'a Filter<'a>,
+        /// If `None`, the results are in playlist order. Requires MPD 0.24
+        sort: Option<Sort<'a>>,
+        /// If `Some`, only returns the results in this range. Requires MPD 0.24
+        window: Option<Range>,
+    },
+    /// Moves the song at position `from` to position `to` in the stored playlist `name`
+    PlaylistMove {
+        /// Name of the stored playlist
+        name: &'a str,
+        /// Current position of the song
+        from: u32,
+        /// New position of the song
+        to: u32,
+    },
     /// Plays previous song in the playlist
     Previous,
+    /// Sets the priority of the songs in `ranges`. In random mode, songs with higher priority are
+    /// played first
+    Prio {
+        /// Priority, the default is `0`
+        priority: u8,
+        /// Songs whose priority is changed
+        ranges: &'a [Range],
+    },
+    /// Sets the priority of the songs with ids `ids`, see `Prio`
+    PrioId {
+        /// Priority, the default is `0`
+        priority: u8,
+        /// Ids of the songs whose priority is changed
+        ids: &'a [u32],
+    },
+    /// Renames the stored playlist `from` to `to`
+    Rename {
+        /// Current name of the stored playlist
+        from: &'a str,
+        /// New name of the stored playlist
+        to: &'a str,
+    },
+    /// Reads the messages received on the channels this client is subscribed to, see
+    /// `parse::messages`
+    ReadMessages,
+    /// Removes the stored playlist `name`
+    Rm {
+        /// Name of the stored playlist
+        name: &'a str,
+    },
+    /// Saves the playlist to the stored playlist `name`
+    Save {
+        /// Name of the stored playlist
+        name: &'a str,
+        /// If `None`, fails if the stored playlist already exists
+        mode: Option<SaveMode>,
+    },
+    /// Searches the database for songs that match `filter` (case insensitive)
+    Search {
+        /// Filter expression
+        filter: &'a Filter<'a>,
+        /// If `None`, the results are not sorted
+        sort: Option<Sort<'a>>,
+        /// If `Some`, only returns the results in this range
+        window: Option<Range>,
+    },
+    /// Adds the songs that match `filter` (case insensitive) to the playlist
+    SearchAdd {
+        /// Filter expression
+        filter: &'a Filter<'a>,
+        /// If `None`, the songs are added in database order
+        sort: Option<Sort<'a>>,
+        /// If `Some`, only adds the songs in this range of the results
+        window: Option<Range>,
+    },
+    /// Adds the songs that match `filter` (case insensitive) to the stored playlist `name`
+    SearchAddPl {
+        /// Name of the stored playlist
+        name: &'a str,
+        /// Filter expression
+        filter: &'a Filter<'a>,
+        /// If `None`, the songs are added in database order
+        sort: Option<Sort<'a>>,
+        /// If `Some`, only adds the songs in this range of the results
+        window: Option<Range>,
+    },
+    /// Seeks to `time` seconds of the song at `position` in the playlist
+    Seek {
+        /// Position of the song
+        position: u32,
+        /// Time in seconds, may be fractional
+        time: f64,
+    },
+    /// Seeks within the current song
+    SeekCur {
+        /// Absolute or relative time
+        time: SeekTarget,
+    },
+    /// Seeks to `time` seconds of the song with id `id`
+    SeekId {
+        /// Song id
+        id: u32,
+        /// Time in seconds, may be fractional
+        time: f64,
+    },
+    /// Sends a message to the clients subscribed to `channel`
+    SendMessage {
+        /// Channel name
+        channel: &'a str,
+        /// Text of the message
+        text: &'a str,
+    },
     /// Sets `mode` to `state`
     Set {
         /// MPD mode
@@ -143,10 +907,93 @@ pub enum Command<'a> {
         /// `true`: mode enabled, `false`: mode disabled
         state: bool,
     },
+    /// Shuffles the playlist
+    Shuffle {
+        /// If `Some`, only shuffles the songs in this range
+        range: Option<Range>,
+    },
     /// Reports the current status of the player and the volume level
     Status,
+    /// Displays statistics of the server and its database, see `Stats::parse`
+    Stats,
+    /// Deletes the sticker `name` from the object `uri`
+    StickerDelete {
+        /// Sticker type, e.g. `song`
+        kind: &'a str,
+        /// URI of the object
+        uri: &'a str,
+        /// If `None`, deletes all the stickers of the object
+        name: Option<&'a str>,
+    },
+    /// Searches the objects within `uri` for the sticker `name`, see `parse::stickers`
+    StickerFind {
+        /// Sticker type, e.g. `song`
+        kind: &'a str,
+        /// URI of a directory, use `""` to search the whole database
+        uri: &'a str,
+        /// Name of the sticker
+        name: &'a str,
+        /// If `Some`, only returns the stickers whose value matches
+        filter: Option<StickerOp<'a>>,
+    },
+    /// Reads the sticker `name` of the object `uri`, see `parse::stickers`
+    StickerGet {
+        /// Sticker type, e.g. `song`
+        kind: &'a str,
+        /// URI of the object
+        uri: &'a str,
+        /// Name of the sticker
+        name: &'a str,
+    },
+    /// Lists the stickers of the object `uri`, see `parse::stickers`
+    StickerList {
+        /// Sticker type, e.g. `song`
+        kind: &'a str,
+        /// URI of the object
+        uri: &'a str,
+    },
+    /// Sets the sticker `name` of the object `uri` to `value`
+    StickerSet {
+        /// Sticker type, e.g. `song`
+        kind: &'a str,
+        /// URI of the object
+        uri: &'a str,
+        /// Name of the sticker
+        name: &'a str,
+        /// Value of the sticker
+        value: &'a str,
+    },
     /// Stops playing
     Stop,
+    /// Subscribes to `channel`, which is created if it doesn't exist
+    Subscribe {
+        /// Channel name, may only contain letters, digits and the characters `-_.:`
+        channel: &'a str,
+    },
+    /// Swaps the positions of two songs in the playlist
+    Swap {
+        /// Position of the first song
+        pos1: u32,
+        /// Position of the second song
+        pos2: u32,
+    },
+    /// Swaps the positions of two songs, specified by their ids, in the playlist
+    SwapId {
+        /// Id of the first song
+        id1: u32,
+        /// Id of the second song
+        id2: u32,
+    },
+    /// Enables the output `id` if it's disabled, and disables it otherwise
+    ToggleOutput {
+        /// Output id
+        id: u32,
+    },
+    /// Unsubscribes from `channel`
+    Unsubscribe {
+        /// Channel name
+        channel: &'a str,
+    },
     /// Updates the music database. `uri` is a particular directory or file to update.
     Update {
         /// If `None`, updates everything
@@ -160,83 +1007,739 @@ pub enum Command<'a> {
 }
 
 impl<'a> Command<'a> {
-    fn str(&self) -> Cow<'static, str> {
+    fn str(&self) -> Result<Cow<'static, str>, Error> {
         use self::Command::*;
 
-        Cow::from(match *self {
-            Add { uri } => return format!("add \"{}\"", uri).into(),
+        Ok(Cow::from(match *self {
+            Add { uri } => return Ok(format!("add {}", try!(quote(uri))).into()),
+            AddId { uri, position: None } => {
+                return Ok(format!("addid {}", try!(quote(uri))).into())
+            }
+            AddId { uri, position: Some(position) } => {
+                return Ok(format!("addid {} {}", try!(quote(uri)), position.str()).into())
+            }
+            Channels => "channels",
             Clear => "clear",
             CurrentSong => "currentsong",
+            Count { filter, group } => {
+                let mut cmd = String::from("count");
+
+                if let Some(filter) = filter {
+                    cmd.push(' ');
+                    cmd.push_str(&try!(expression(filter)));
+                }
+
+                if let Some(group) = group {
+                    cmd.push_str(" group ");
+                    cmd.push_str(&try!(quote(group)));
+                }
+
+                return Ok(cmd.into());
+            }
+            Delete { range } => return Ok(format!("delete {}", range.str()).into()),
+            DeleteId { id } => return Ok(format!("deleteid {}", id).into()),
+            DisableOutput { id } => return Ok(format!("disableoutput {}", id).into()),
+            EnableOutput { id } => return Ok(format!("enableoutput {}", id).into()),
+            Find { filter, sort, window } => {
+                return query("find", filter, sort, window).map(Cow::from)
+            }
+            FindAdd { filter, sort, window } => {
+                return query("findadd", filter, sort, window).map(Cow::from)
+            }
+            Idle { subsystems } => {
+                let mut cmd = String::from("idle");
+
+                for subsystem in subsystems {
+                    cmd.push(' ');
+                    cmd.push_str(subsystem.str());
+                }
+
+                return Ok(cmd.into());
+            }
+            List { tag, filter, group } => {
+                let mut cmd = format!("list {}", try!(quote(tag)));
+
+                if let Some(filter) = filter {
+                    cmd.push(' ');
+                    cmd.push_str(&try!(expression(filter)));
+                }
+
+                // MPD nests the groups in the reverse order of the arguments
+                for tag in group.iter().rev() {
+                    cmd.push_str(" group ");
+                    cmd.push_str(&try!(quote(tag)));
+                }
+
+                return Ok(cmd.into());
+            }
             ListAll { uri: None } => "listall",
-            ListAll { uri: Some(uri) } => return format!("listall \"{}\"", uri).into(),
+            ListAll { uri: Some(uri) } => {
+                return Ok(format!("listall {}", try!(quote(uri))).into())
+            }
+            ListAllInfo { uri: None } => "listallinfo",
+            ListAllInfo { uri: Some(uri) } => {
+                return Ok(format!("listallinfo {}", try!(quote(uri))).into())
+            }
+            ListFiles { uri: None } => "listfiles",
+            ListFiles { uri: Some(uri) } => {
+                return Ok(format!("listfiles {}", try!(quote(uri))).into())
+            }
+            ListPlaylist { name } => {
+                return Ok(format!("listplaylist {}", try!(quote(name))).into())
+            }
+            ListPlaylistInfo { name } => {
+                return Ok(format!("listplaylistinfo {}", try!(quote(name))).into())
+            }
+            ListPlaylists => "listplaylists",
+            LsInfo { uri: None } => "lsinfo",
+            LsInfo { uri: Some(uri) } => return Ok(format!("lsinfo {}", try!(quote(uri))).into()),
+            Load { name, range, position } => {
+                let mut cmd = format!("load {}", try!(quote(name)));
+
+                // the position can only be specified after a range
+                let range = range.or_else(|| {
+                    position.map(|_| {
+                        Range {
+                            start: 0,
+                            end: None,
+                        }
+                    })
+                });
+
+                if let Some(range) = range {
+                    cmd.push(' ');
+                    cmd.push_str(&range.str());
+                }
+
+                if let Some(position) = position {
+                    cmd.push(' ');
+                    cmd.push_str(&position.str());
+                }
+
+                return Ok(cmd.into());
+            }
+            Move { from, to } => return Ok(format!("move {} {}", from.str(), to.str()).into()),
+            MoveId { id, to } => return Ok(format!("moveid {} {}", id, to.str()).into()),
             Next => "next",
+            NoIdle => "noidle",
             Pause { state: false } => "pause 0",
             Pause { state: true } => "pause 1",
+            OutputSet { id, name, value } => {
+                return Ok(format!("outputset {} {} {}", id, try!(quote(name)), try!(quote(value)))
+                              .into())
+            }
+            Outputs => "outputs",
+            Password { password } => {
+                return Ok(format!("password {}", try!(quote(password))).into())
+            }
             Play { position: None } => "play",
-            Play { position: Some(pos) } => return format!("play {}", pos).into(),
+            Play { position: Some(pos) } => return Ok(format!("play {}", pos).into()),
+            PlaylistAdd { name, uri, position: None } => {
+                return Ok(format!("playlistadd {} {}", try!(quote(name)), try!(quote(uri))).into())
+            }
+            PlaylistAdd { name, uri, position: Some(pos) } => {
+                return Ok(format!("playlistadd {} {} {}",
+                                  try!(quote(name)),
+                                  try!(quote(uri)),
+                                  pos)
+                              .into())
+            }
+            PlaylistClear { name } => {
+                return Ok(format!("playlistclear {}", try!(quote(name))).into())
+            }
+            PlaylistDelete { name, range } => {
+                return Ok(format!("playlistdelete {} {}", try!(quote(name)), range.str()).into())
+            }
             PlaylistInfo => "playlistinfo",
+            PlaylistMove { name, from, to } => {
+                return Ok(format!("playlistmove {} {} {}", try!(quote(name)), from, to).into())
+            }
+            PlaylistSearch { filter, sort, window } => {
+                return query("playlistsearch", filter, sort, window).map(Cow::from)
+            }
             Previous => "previous",
+            Prio { priority, ranges } => {
+                let mut cmd = format!("prio {}", priority);
+
+                for range in ranges {
+                    cmd.push(' ');
+                    cmd.push_str(&range.str());
+                }
+
+                return Ok(cmd.into());
+            }
+            PrioId { priority, ids } => {
+                let mut cmd = format!("prioid {}", priority);
+
+                for id in ids {
+                    cmd.push(' ');
+                    cmd.push_str(&id.to_string());
+                }
+
+                return Ok(cmd.into());
+            }
+            ReadMessages => "readmessages",
+            Rename { from, to } => {
+                return Ok(format!("rename {} {}", try!(quote(from)), try!(quote(to))).into())
+            }
+            Rm { name } => return Ok(format!("rm {}", try!(quote(name))).into()),
+            Save { name, mode: None } => return Ok(format!("save {}", try!(quote(name))).into()),
+            Save { name, mode: Some(ref mode) } => {
+                return Ok(format!("save {} {}", try!(quote(name)), mode.str()).into())
+            }
+            Search { filter, sort, window } => {
+                return query("search", filter, sort, window).map(Cow::from)
+            }
+            SearchAdd { filter, sort, window } => {
+                return query("searchadd", filter, sort, window).map(Cow::from)
+            }
+            SearchAddPl { name, filter, sort, window } => {
+                let cmd = format!("searchaddpl {}", try!(quote(name)));
+                return query(&cmd, filter, sort, window).map(Cow::from);
+            }
+            Seek { position, time } => {
+                return Ok(format!("seek {} {}", position, try!(seconds(time))).into())
+            }
+            SeekCur { time } => return Ok(format!("seekcur {}", try!(time.str())).into()),
+            SeekId { id, time } => {
+                return Ok(format!("seekid {} {}", id, try!(seconds(time))).into())
+            }
+            SendMessage { channel, text } => {
+                return Ok(format!("sendmessage {} {}", try!(quote(channel)), try!(quote(text)))
+                              .into())
+            }
             Set { ref mode, state } => {
-                return format!("{} {}",
-                               mode.str(),
-                               if state {
-                                   "1"
-                               } else {
-                                   "0"
-                               })
-                           .into()
+                return Ok(format!("{} {}",
+                                  mode.str(),
+                                  if state {
+                                      "1"
+                                  } else {
+                                      "0"
+                                  })
+                              .into())
             }
+            Shuffle { range: None } => "shuffle",
+            Shuffle { range: Some(range) } => return Ok(format!("shuffle {}", range.str()).into()),
             Status => "status",
+            Stats => "stats",
+            StickerDelete { kind, uri, name } => {
+                let mut cmd = format!("sticker delete {} {}", try!(quote(kind)), try!(quote(uri)));
+
+                if let Some(name) = name {
+                    cmd.push(' ');
+                    cmd.push_str(&try!(quote(name)));
+                }
+
+                return Ok(cmd.into());
+            }
+            StickerFind { kind, uri, name, filter } => {
+                let mut cmd = format!("sticker find {} {} {}",
+                                      try!(quote(kind)),
+                                      try!(quote(uri)),
+                                      try!(quote(name)));
+
+                if let Some(filter) = filter {
+                    cmd.push(' ');
+                    cmd.push_str(&try!(filter.str()));
+                }
+
+                return Ok(cmd.into());
+            }
+            StickerGet { kind, uri, name } => {
+                return Ok(format!("sticker get {} {} {}",
+                                  try!(quote(kind)),
+                                  try!(quote(uri)),
+                                  try!(quote(name)))
+                              .into())
+            }
+            StickerList { kind, uri } => {
+                return Ok(format!("sticker list {} {}", try!(quote(kind)), try!(quote(uri)))
+                              .into())
+            }
+            StickerSet { kind, uri, name, value } => {
+                return Ok(format!("sticker set {} {} {} {}",
+                                  try!(quote(kind)),
+                                  try!(quote(uri)),
+                                  try!(quote(name)),
+                                  try!(quote(value)))
+                              .into())
+            }
             Stop => "stop",
+            Subscribe { channel } => {
+                return Ok(format!("subscribe {}", try!(quote(channel))).into())
+            }
+            Swap { pos1, pos2 } => return Ok(format!("swap {} {}", pos1, pos2).into()),
+            SwapId { id1, id2 } => return Ok(format!("swapid {} {}", id1, id2).into()),
+            ToggleOutput { id } => return Ok(format!("toggleoutput {}", id).into()),
+            Unsubscribe { channel } => {
+                return Ok(format!("unsubscribe {}", try!(quote(channel))).into())
+            }
             Update { uri: None } => "update",
-            Update { uri: Some(uri) } => return format!("update \"{}\"", uri).into(),
-            Volume { level } => return format!("setvol {}", level).into(),
-        })
+            Update { uri: Some(uri) } => {
+                return Ok(format!("update {}", try!(quote(uri))).into())
+            }
+            Volume { level } => return Ok(format!("setvol {}", level).into()),
+        }))
+    }
+}
+
+/// Formats `{cmd} {filter} [sort {tag}] [window {range}]`
+fn query(cmd: &str,
+         filter: &Filter,
+         sort: Option<Sort>,
+         window: Option<Range>)
+         -> Result<String, Error> {
+    let mut query = format!("{} {}", cmd, try!(expression(filter)));
+
+    if let Some(sort) = sort {
+        // A leading `-` would flip the order, that's what `descending` is for
+        if sort.tag.starts_with('-') {
+            return Err(Error::InvalidArgument(sort.tag.to_owned()));
+        }
+        try!(filter::check_tag(sort.tag));
+        query.push_str(" sort ");
+        if sort.descending {
+            query.push('-');
+        }
+        query.push_str(sort.tag);
+    }
+
+    if let Some(window) = window {
+        query.push_str(" window ");
+        query.push_str(&window.str());
     }
+
+    Ok(query)
 }
 
-/// A connection to MPD
-pub struct Connection {
+/// Formats a number of seconds, which must be finite and positive
+fn seconds(time: f64) -> Result<String, Error> {
+    if time.is_finite() && time >= 0. {
+        Ok(time.to_string())
+    } else {
+        Err(Error::InvalidArgument(time.to_string()))
+    }
+}
+
+/// Renders `filter` as a quoted filter expression
+fn expression(filter: &Filter) -> Result<String, Error> {
+    try!(filter.check());
+    quote(&filter.to_string())
+}
+
+/// Quotes `arg` so MPD parses it as a single argument
+///
+/// Double quotes and backslashes are escaped. Control characters (e.g. newlines) can't be escaped
+/// in the protocol and are rejected.
+fn quote(arg: &str) -> Result<String, Error> {
+    if arg.chars().any(char::is_control) {
+        return Err(Error::InvalidArgument(arg.to_owned()));
+    }
+
+    let mut quoted = String::with_capacity(arg.len() + 2);
+
+    quoted.push('"');
+    for c in arg.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+
+    Ok(quoted)
+}
+
+/// A socket connected to MPD, the default transport of `Connection`
+pub enum Stream {
+    /// TCP socket
+    Tcp(TcpStream),
+    /// Unix domain socket
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match *self {
+            Stream::Tcp(ref mut s) => s.read(buf),
+            #[cfg(unix)]
+            Stream::Unix(ref mut s) => s.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match *self {
+            Stream::Tcp(ref mut s) => s.write(buf),
+            #[cfg(unix)]
+            Stream::Unix(ref mut s) => s.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match *self {
+            Stream::Tcp(ref mut s) => s.flush(),
+            #[cfg(unix)]
+            Stream::Unix(ref mut s) => s.flush(),
+        }
+    }
+}
+
+/// A connection to MPD over the bidirectional byte stream `S`
+pub struct Connection<S = Stream>
+    where S: Read + Write
+{
     buffer: String,
-    stream: BufStream<TcpStream>,
+    stream: BufStream<S>,
     version: Version,
 }
 
-impl Connection {
+impl<S> Connection<S>
+    where S: Read + Write
+{
+    /// Starts a session over `stream`, which must be connected to MPD
+    ///
+    /// This reads MPD's greeting, use `connect` or `connect_unix` to connect to a socket. Fails
+    /// with `io::ErrorKind::InvalidData` if the peer doesn't greet like MPD does
+    pub fn new(stream: S) -> io::Result<Connection<S>> {
+        let mut stream = BufStream::new(stream);
+        let mut buffer = String::new();
+
+        try!(stream.read_line(&mut buffer));
+
+        if !buffer.starts_with("OK MPD ") {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                      format!("expected 'OK MPD {{version}}', got {:?}", buffer)));
+        }
+
+        let version = {
+            let version = buffer["OK MPD ".len()..].trim_right();
+            try!(Version::parse(version).map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidData,
+                               format!("couldn't parse {:?} as a MPD version", version))
+            }))
+        };
+
+        buffer.clear();
+        Ok(Connection {
+            buffer: buffer,
+            stream: stream,
+            version: version,
+        })
+    }
+
     /// Sends a command to MPD
-    pub fn send(&mut self, cmd: Command) -> io::Result<()> {
+    pub fn send(&mut self, cmd: Command) -> Result<(), Error> {
+        let cmd = try!(cmd.str());
         let stream = &mut self.stream;
-        try!(writeln!(stream, "{}", cmd.str()));
-        stream.flush()
+        try!(writeln!(stream, "{}", cmd));
+        try!(stream.flush());
+        Ok(())
     }
 
     /// Returns command output
-    pub fn recv(&mut self) -> io::Result<&str> {
+    ///
+    /// If MPD failed to execute the command, returns the `Ack` it reported
+    pub fn recv(&mut self) -> Result<&str, Error> {
+        self.buffer.clear();
+
+        while try!(self.read_line()) {}
+
+        Ok(self.buffer.trim_right_matches('\n'))
+    }
+
+    /// Returns the command output as a stream of `{key}: {value}` pairs
+    ///
+    /// Unlike `recv`, this doesn't buffer the whole output. Any pair left unread when the iterator
+    /// is dropped gets discarded.
+    pub fn recv_pairs(&mut self) -> Pairs<S> {
+        Pairs {
+            conn: self,
+            done: false,
+        }
+    }
+
+    /// Returns the command output as a stream of records, each one starting at a `file`,
+    /// `directory` or `playlist` key
+    ///
+    /// Each record can be parsed with e.g. `Song::parse`. Any record left unread when the iterator
+    /// is dropped gets discarded.
+    pub fn recv_records(&mut self) -> RecordStream<S> {
+        RecordStream {
+            conn: self,
+            done: false,
+            record: String::new(),
+        }
+    }
+
+    /// Appends the next line of the command output to `buffer`
+    ///
+    /// Returns `false`, without appending anything, once the end of the output (`OK`) is reached
+    fn read_line(&mut self) -> Result<bool, Error> {
         let Connection { ref mut buffer, ref mut stream, .. } = *self;
 
-        buffer.clear();
+        let start = buffer.len();
 
-        try!(stream.read_line(buffer));
+        if try!(stream.read_line(buffer)) == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed by MPD")
+                           .into());
+        }
 
-        if buffer.starts_with("ACK") {
-            // TODO lift error
-            panic!("BUG: unhandled server error: {}", buffer.trim_right());
+        if buffer[start..].starts_with("ACK") {
+            match Ack::parse(buffer[start..].trim_right()) {
+                Ok(ack) => Err(Error::Ack(ack)),
+                Err(e) => Err(Error::from(e)),
+            }
+        } else if &buffer[start..] == "OK\n" {
+            buffer.truncate(start);
+            Ok(false)
         } else {
-            // End Of Message
-            const EOM: &'static str = "OK\n";
+            Ok(true)
+        }
+    }
 
-            while !buffer.ends_with(EOM) {
-                try!(stream.read_line(buffer));
+    /// Sends `cmds` as a command list, i.e. in a single round trip
+    ///
+    /// Use `recv_list` to retrieve the output of each command
+    pub fn send_list(&mut self, cmds: &[Command]) -> Result<(), Error> {
+        let mut list = String::from("command_list_ok_begin\n");
+
+        for cmd in cmds {
+            list.push_str(&try!(cmd.str()));
+            list.push('\n');
+        }
+        list.push_str("command_list_end\n");
+
+        let stream = &mut self.stream;
+        try!(stream.write_all(list.as_bytes()));
+        try!(stream.flush());
+        Ok(())
+    }
+
+    /// Returns the output of each command in the command list sent by `send_list`
+    ///
+    /// MPD stops executing the command list at the first command that fails. In that case, this
+    /// returns the `Ack` of that command; its `index` is the position of the command in the list.
+    pub fn recv_list(&mut self) -> Result<Vec<&str>, Error> {
+        self.buffer.clear();
+
+        // (start, end) of the output of each command
+        let mut outputs = vec![];
+        let mut start = 0;
+        loop {
+            let line_start = self.buffer.len();
+
+            if !try!(self.read_line()) {
+                break;
             }
 
-            Ok(buffer[..buffer.len() - EOM.len()].trim_right())
+            if &self.buffer[line_start..] == "list_OK\n" {
+                outputs.push((start, line_start));
+                start = self.buffer.len();
+            }
         }
+
+        let buffer = &self.buffer[..];
+        Ok(outputs.into_iter()
+               .map(move |(start, end)| buffer[start..end].trim_right_matches('\n'))
+               .collect())
+    }
+
+    /// Authenticates with `password`
+    ///
+    /// Returns an `Ack` with code `AckCode::Password` if the password is wrong
+    pub fn password(&mut self, password: &str) -> Result<(), Error> {
+        try!(self.send(Command::Password { password: password }));
+        try!(self.recv());
+        Ok(())
     }
 
     /// Returns MPD version
     pub fn version(&self) -> &Version {
         &self.version
     }
+
+    /// Turns this connection into a blocking iterator over changes in `subsystems`
+    ///
+    /// If `subsystems` is empty, changes in all the subsystems are reported
+    pub fn events(self, subsystems: &[Subsystem]) -> Events<S> {
+        Events {
+            conn: self,
+            done: false,
+            pending: VecDeque::new(),
+            subsystems: subsystems.to_owned(),
+        }
+    }
+}
+
+/// Streaming iterator over the `{key}: {value}` pairs of a command output
+///
+/// Created with `Connection::recv_pairs`
+pub struct Pairs<'a, S = Stream>
+    where S: 'a + Read + Write
+{
+    conn: &'a mut Connection<S>,
+    done: bool,
+}
+
+impl<'a, S> Iterator for Pairs<'a, S>
+    where S: Read + Write
+{
+    type Item = Result<(String, String), Error>;
+
+    fn next(&mut self) -> Option<Result<(String, String), Error>> {
+        if self.done {
+            return None;
+        }
+
+        self.conn.buffer.clear();
+        match self.conn.read_line() {
+            Ok(true) => {
+                let line = self.conn.buffer.trim_right_matches('\n');
+
+                Some(parse::pair(line)
+                         .map(|(k, v)| (k.to_owned(), v.to_owned()))
+                         .map_err(Error::from))
+            }
+            Ok(false) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl<'a, S> Drop for Pairs<'a, S>
+    where S: Read + Write
+{
+    fn drop(&mut self) {
+        for _ in self {}
+    }
+}
+
+/// Streaming iterator over the records of a command output, split like `parse::records` does
+///
+/// Created with `Connection::recv_records`
+pub struct RecordStream<'a, S = Stream>
+    where S: 'a + Read + Write
+{
+    conn: &'a mut Connection<S>,
+    done: bool,
+    record: String,
+}
+
+impl<'a, S> Iterator for RecordStream<'a, S>
+    where S: Read + Write
+{
+    type Item = Result<String, Error>;
+
+    fn next(&mut self) -> Option<Result<String, Error>> {
+        if self.done {
+            return None;
+        }
+
+        loop {
+            self.conn.buffer.clear();
+            match self.conn.read_line() {
+                Ok(true) => {
+                    let line = self.conn.buffer.trim_right_matches('\n');
+
+                    if parse::starts_record(line) && !self.record.is_empty() {
+                        return Some(Ok(mem::replace(&mut self.record, line.to_owned())));
+                    }
+
+                    if !self.record.is_empty() {
+                        self.record.push('\n');
+                    }
+                    self.record.push_str(line);
+                }
+                Ok(false) => {
+                    self.done = true;
+
+                    if self.record.is_empty() {
+                        return None;
+                    } else {
+                        return Some(Ok(mem::replace(&mut self.record, String::new())));
+                    }
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+impl<'a, S> Drop for RecordStream<'a, S>
+    where S: Read + Write
+{
+    fn drop(&mut self) {
+        for _ in self {}
+    }
+}
+
+/// Blocking iterator over the changes in MPD's subsystems
+///
+/// Created with `Connection::events`. Each call to `next` blocks until MPD reports a change. The
+/// iterator ends after the first error.
+pub struct Events<S = Stream>
+    where S: Read + Write
+{
+    conn: Connection<S>,
+    done: bool,
+    pending: VecDeque<Subsystem>,
+    subsystems: Vec<Subsystem>,
+}
+
+impl<S> Events<S>
+    where S: Read + Write
+{
+    /// Returns the underlying connection
+    pub fn into_inner(self) -> Connection<S> {
+        self.conn
+    }
+
+    /// Reads the messages received on the subscribed channels, call this after a
+    /// `Subsystem::Message` event
+    pub fn read_messages(&mut self) -> Result<Vec<Message>, Error> {
+        try!(self.conn.send(Command::ReadMessages));
+        Ok(try!(parse::messages(try!(self.conn.recv()))))
+    }
+
+    fn idle(&mut self) -> Result<(), Error> {
+        try!(self.conn.send(Command::Idle { subsystems: &self.subsystems }));
+        let changed = try!(parse::changed(try!(self.conn.recv())));
+        self.pending.extend(changed);
+        Ok(())
+    }
+}
+
+impl<S> Iterator for Events<S>
+    where S: Read + Write
+{
+    type Item = Result<Subsystem, Error>;
+
+    fn next(&mut self) -> Option<Result<Subsystem, Error>> {
+        while self.pending.is_empty() {
+            if self.done {
+                return None;
+            }
+
+            if let Err(e) = self.idle() {
+                self.done = true;
+                return Some(Err(e));
+            }
+        }
+
+        self.pending.pop_front().map(Ok)
+    }
 }
 
 /// MPD version
@@ -281,32 +1784,559 @@ impl Version {
 pub fn connect<A>(addr: A) -> io::Result<Connection>
     where A: ToSocketAddrs
 {
-    fn new(stream: TcpStream) -> io::Result<Connection> {
-        let mut stream = BufStream::new(stream);
-        let mut buffer = String::new();
+    Connection::new(Stream::Tcp(try!(TcpStream::connect(addr))))
+}
 
-        try!(stream.read_line(&mut buffer));
+/// Connects to the MPD listening on the Unix domain socket at `path` (e.g. `/run/mpd/socket`)
+#[cfg(unix)]
+pub fn connect_unix<P>(path: P) -> io::Result<Connection>
+    where P: AsRef<Path>
+{
+    Connection::new(Stream::Unix(try!(UnixStream::connect(path))))
+}
 
-        if !buffer.starts_with("OK MPD ") {
-            // TODO lift error
-            panic!("BUG: unhandled server error: expected 'OK MPD {{version}}' got '{}'",
-                   buffer)
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Cursor, Read, Write};
+
+    use super::{AckCode, Command, Connection, Error, Range, SeekTarget, Sort, Status, StickerOp,
+                parse, quote};
+    use filter::{Filter, Op};
+
+    /// In-memory transport that replays `input` and records what the client sends
+    struct Mock {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for Mock {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Mock {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
         }
 
-        let version = {
-            let version = &buffer["OK MPD ".len()..].trim_right();
-            Version::parse(version).unwrap_or_else(|_| {
-                panic!("BUG: error parsing '{}' as Version", version);
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// A connection whose server greets the client and then replies with `replies`
+    fn connection(replies: &str) -> Connection<Mock> {
+        Connection::new(Mock {
+                input: Cursor::new(format!("OK MPD 0.23.5\n{}", replies).into_bytes()),
+                output: vec![],
             })
+            .unwrap()
+    }
+
+    #[test]
+    fn invalid_greeting() {
+        for &greeting in &["HTTP/1.1 400 Bad Request\n", "OK MPD x.y.z\n", ""] {
+            let mock = Mock {
+                input: Cursor::new(greeting.as_bytes().to_owned()),
+                output: vec![],
+            };
+
+            match Connection::new(mock) {
+                Err(ref e) if e.kind() == io::ErrorKind::InvalidData => {}
+                _ => panic!("{:?} was accepted", greeting),
+            }
+        }
+    }
+
+    #[test]
+    fn recv_only_stops_at_an_ok_line() {
+        let mut conn = connection("Title: OK\nfile: OK\nOK\n");
+
+        assert_eq!(conn.recv().unwrap(), "Title: OK\nfile: OK");
+    }
+
+    #[test]
+    fn recv_keeps_trailing_empty_values() {
+        let mut conn = connection("AlbumArtist: A\nAlbum: \nOK\nlist_OK\nAlbum: \nlist_OK\nOK\n");
+
+        assert_eq!(conn.recv().unwrap(), "AlbumArtist: A\nAlbum: ");
+        assert_eq!(conn.recv_list().unwrap(), ["", "Album: "]);
+    }
+
+    #[test]
+    fn status_ending_with_an_empty_value() {
+        let mut conn = connection("volume: 50\nrepeat: 0\nrandom: 0\nsingle: 0\nconsume: 0\n\
+                                   playlist: 1\nplaylistlength: 0\nstate: stop\n\
+                                   lastloadedplaylist: \nOK\n");
+
+        let status = Status::parse(conn.recv().unwrap()).unwrap_or_else(|e| parse::bug(e));
+        assert_eq!(status.last_loaded_playlist, None);
+    }
+
+    #[test]
+    fn recv_eof() {
+        let mut conn = connection("file: a.flac\n");
+
+        match conn.recv() {
+            Err(Error::Io(ref e)) if e.kind() == io::ErrorKind::UnexpectedEof => {}
+            _ => panic!("expected an unexpected EOF error"),
+        }
+    }
+
+    #[test]
+    fn pairs() {
+        let mut conn = connection("file: a.flac\nTitle: OK\nOK\n");
+
+        let pairs = conn.recv_pairs().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(pairs,
+                   [("file".to_owned(), "a.flac".to_owned()),
+                    ("Title".to_owned(), "OK".to_owned())]);
+    }
+
+    #[test]
+    fn pairs_are_drained_on_drop() {
+        let mut conn = connection("a: 1\nb: 2\nc: 3\nOK\nvolume: 50\nOK\n");
+
+        assert_eq!(conn.recv_pairs().next().unwrap().unwrap(),
+                   ("a".to_owned(), "1".to_owned()));
+        assert_eq!(conn.recv().unwrap(), "volume: 50");
+    }
+
+    #[test]
+    fn records() {
+        let mut conn = connection("directory: a\nLast-Modified: 2020-01-01T00:00:00Z\n\
+                                   file: a/1.flac\nTitle: OK\nplaylist: a/p.m3u\nfile: b.ogg\n\
+                                   OK\n");
+
+        let records = conn.recv_records().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(records,
+                   ["directory: a\nLast-Modified: 2020-01-01T00:00:00Z",
+                    "file: a/1.flac\nTitle: OK",
+                    "playlist: a/p.m3u",
+                    "file: b.ogg"]);
+    }
+
+    #[test]
+    fn records_are_drained_on_drop() {
+        let mut conn = connection("file: a\nfile: b\nfile: c\nOK\nvolume: 50\nOK\n");
+
+        assert_eq!(conn.recv_records().next().unwrap().unwrap(), "file: a");
+        assert_eq!(conn.recv().unwrap(), "volume: 50");
+    }
+
+    #[test]
+    fn ack_in_the_middle_of_a_stream() {
+        let mut conn = connection("file: a\nfile: b\nACK [52@0] {find} I/O error\n\
+                                   volume: 50\nOK\n");
+
+        {
+            let mut records = conn.recv_records();
+
+            assert_eq!(records.next().unwrap().unwrap(), "file: a");
+            match records.next() {
+                Some(Err(Error::Ack(ref ack))) if ack.message == "I/O error" => {}
+                _ => panic!("expected an ACK"),
+            }
+            assert!(records.next().is_none());
+        }
+
+        // the ACK ends the output, the next reply is intact
+        assert_eq!(conn.recv().unwrap(), "volume: 50");
+    }
+
+    #[test]
+    fn recv_list() {
+        let mut conn = connection("list_OK\nvolume: 50\nrepeat: 0\nlist_OK\nOK\n");
+
+        conn.send_list(&[Command::Clear, Command::Status]).unwrap();
+        assert_eq!(conn.recv_list().unwrap(), ["", "volume: 50\nrepeat: 0"]);
+        assert_eq!(&conn.stream.get_ref().output[..],
+                   &b"command_list_ok_begin\nclear\nstatus\ncommand_list_end\n"[..]);
+    }
+
+    #[test]
+    fn recv_list_ack() {
+        let mut conn = connection("list_OK\nACK [2@1] {play} Bad song index\nvolume: 50\nOK\n");
+
+        conn.send_list(&[Command::Clear, Command::Play { position: Some(9) }, Command::Status])
+            .unwrap();
+        match conn.recv_list() {
+            Err(Error::Ack(ref ack)) if ack.index == 1 && ack.command == "play" => {}
+            _ => panic!("expected the ACK of the second command"),
+        }
+        assert_eq!(conn.recv().unwrap(), "volume: 50");
+    }
+
+    #[test]
+    fn events() {
+        use super::Subsystem;
+
+        let conn = connection("changed: player\nchanged: mixer\nOK\nchanged: options\nOK\n");
+        let mut events = conn.events(&[Subsystem::Player, Subsystem::Mixer, Subsystem::Options]);
+
+        assert_eq!(events.by_ref().take(3).collect::<Result<Vec<_>, _>>().unwrap(),
+                   [Subsystem::Player, Subsystem::Mixer, Subsystem::Options]);
+        match events.next() {
+            Some(Err(Error::Io(ref e))) if e.kind() == io::ErrorKind::UnexpectedEof => {}
+            _ => panic!("expected an unexpected EOF error"),
+        }
+        assert!(events.next().is_none());
+        assert_eq!(&events.into_inner().stream.get_ref().output[..],
+                   &b"idle player mixer options\n".repeat(3)[..]);
+    }
+
+    #[test]
+    fn password() {
+        let mut conn = connection("OK\nACK [3@0] {password} incorrect password\n");
+
+        conn.password("secret").unwrap();
+        match conn.password("wrong") {
+            Err(Error::Ack(ref ack)) if ack.code == AckCode::Password => {}
+            _ => panic!("expected a password error"),
+        }
+        assert_eq!(&conn.stream.get_ref().output[..],
+                   &b"password \"secret\"\npassword \"wrong\"\n"[..]);
+    }
+
+    #[test]
+    fn malformed_ack_is_an_error() {
+        let mut conn = connection("ACK garbage\n");
+
+        conn.send(Command::Status).unwrap();
+        match conn.recv() {
+            Err(Error::Parse(_)) => {}
+            _ => panic!("expected a parse error"),
+        }
+    }
+
+    #[test]
+    fn quote_plain() {
+        assert_eq!(quote("Artist/Album/01 Song.flac").unwrap(),
+                   r#""Artist/Album/01 Song.flac""#);
+    }
+
+    #[test]
+    fn quote_escapes_quotes_and_backslashes() {
+        assert_eq!(quote(r#"12" Single.mp3"#).unwrap(), r#""12\" Single.mp3""#);
+        assert_eq!(quote(r"back\slash.ogg").unwrap(), r#""back\\slash.ogg""#);
+        assert_eq!(quote(r"trailing\").unwrap(), r#""trailing\\""#);
+        assert_eq!(quote(r#"\""#).unwrap(), r#""\\\"""#);
+    }
+
+    #[test]
+    fn quote_rejects_control_characters() {
+        for &arg in &["song.mp3\"\nclear", "a\rb", "nul\0.flac", "tab\t.flac"] {
+            match quote(arg) {
+                Err(Error::InvalidArgument(ref s)) if s == arg => {}
+                _ => panic!("{:?} wasn't rejected", arg),
+            }
+        }
+    }
+
+    #[test]
+    fn command_injection() {
+        assert!(Command::Add { uri: "x\"\nclear\nadd \"y" }.str().is_err());
+        assert!(Command::Password { password: "secret\nclear" }.str().is_err());
+        assert_eq!(Command::Add { uri: r#"x" ; clear"# }.str().unwrap(),
+                   r#"add "x\" ; clear""#);
+        assert_eq!(Command::Update { uri: Some(r"dir\") }.str().unwrap(),
+                   r#"update "dir\\""#);
+    }
+
+    #[test]
+    fn filter_expression() {
+        let filter = Filter::And(vec![Filter::Tag {
+                                          tag: "Artist",
+                                          op: Op::Eq,
+                                          value: "Guns N' Roses",
+                                      },
+                                      Filter::Not(Box::new(Filter::Tag {
+                                          tag: "Album",
+                                          op: Op::Contains,
+                                          value: r"Live\Demo",
+                                      })),
+                                      Filter::Base { uri: "rock" }]);
+
+        assert_eq!(filter.to_string(),
+                   concat!(r"((Artist == 'Guns N\' Roses') AND ",
+                           r"(!(Album contains 'Live\\Demo')) AND (base 'rock'))"));
+    }
+
+    #[test]
+    fn invalid_filters_are_rejected() {
+        let find = |filter: &Filter| {
+            Command::Find {
+                    filter: filter,
+                    sort: None,
+                    window: None,
+                }
+                .str()
         };
 
-        buffer.clear();
-        Ok(Connection {
-            buffer: buffer,
-            stream: stream,
-            version: version,
-        })
+        assert!(find(&Filter::And(vec![])).is_err());
+        assert!(find(&Filter::Not(Box::new(Filter::And(vec![])))).is_err());
+        for &tag in &["x) OR (any", "", "Artist "] {
+            assert!(find(&Filter::Tag {
+                            tag: tag,
+                            op: Op::Eq,
+                            value: "x",
+                        })
+                        .is_err(),
+                    "{:?} was accepted",
+                    tag);
+        }
+        assert!(find(&Filter::Tag {
+                        tag: "MUSICBRAINZ_TRACKID",
+                        op: Op::Eq,
+                        value: "x) OR (any",
+                    })
+                    .is_ok());
+
+        let filter = Filter::Tag {
+            tag: "Artist",
+            op: Op::Eq,
+            value: "x",
+        };
+        for &tag in &["Artist\nclear", "-Artist", "", "Artist Album"] {
+            assert!(Command::Find {
+                            filter: &filter,
+                            sort: Some(Sort {
+                                tag: tag,
+                                descending: false,
+                            }),
+                            window: None,
+                        }
+                        .str()
+                        .is_err(),
+                    "{:?} was accepted",
+                    tag);
+        }
+        assert_eq!(Command::Find {
+                           filter: &filter,
+                           sort: Some(Sort {
+                               tag: "Last-Modified",
+                               descending: true,
+                           }),
+                           window: None,
+                       }
+                       .str()
+                       .unwrap(),
+                   "find \"(Artist == 'x')\" sort -Last-Modified");
+    }
+
+    #[test]
+    fn find_quotes_the_expression() {
+        let filter = Filter::Tag {
+            tag: "Title",
+            op: Op::Eq,
+            value: r#"It's "quoted""#,
+        };
+
+        assert_eq!(Command::Find {
+                           filter: &filter,
+                           sort: Some(Sort {
+                               tag: "Track",
+                               descending: true,
+                           }),
+                           window: Some(Range {
+                               start: 0,
+                               end: Some(10),
+                           }),
+                       }
+                       .str()
+                       .unwrap(),
+                   r#"find "(Title == 'It\\'s \"quoted\"')" sort -Track window 0:10"#);
+    }
+
+    #[test]
+    fn list_groups() {
+        assert_eq!(Command::List {
+                           tag: "Album",
+                           filter: None,
+                           group: &["AlbumArtist", "Date"],
+                       }
+                       .str()
+                       .unwrap(),
+                   r#"list "Album" group "Date" group "AlbumArtist""#);
+    }
+
+    #[test]
+    fn playlist_editing() {
+        use super::Position;
+
+        assert_eq!(Command::Delete { range: Range::from(3) }.str().unwrap(), "delete 3");
+        assert_eq!(Command::Delete {
+                           range: Range {
+                               start: 3,
+                               end: Some(5),
+                           },
+                       }
+                       .str()
+                       .unwrap(),
+                   "delete 3:5");
+        assert_eq!(Command::Delete { range: Range::from(::std::u32::MAX) }.str().unwrap(),
+                   "delete 4294967295:");
+        assert_eq!(Command::Move {
+                           from: Range {
+                               start: 2,
+                               end: None,
+                           },
+                           to: Position::After(0),
+                       }
+                       .str()
+                       .unwrap(),
+                   "move 2: +0");
+        assert_eq!(Command::MoveId {
+                           id: 7,
+                           to: Position::Before(1),
+                       }
+                       .str()
+                       .unwrap(),
+                   "moveid 7 -1");
+        assert_eq!(Command::MoveId {
+                           id: 7,
+                           to: Position::Absolute(4),
+                       }
+                       .str()
+                       .unwrap(),
+                   "moveid 7 4");
+        assert_eq!(Command::Swap {
+                           pos1: 1,
+                           pos2: 2,
+                       }
+                       .str()
+                       .unwrap(),
+                   "swap 1 2");
+        assert_eq!(Command::Shuffle { range: None }.str().unwrap(), "shuffle");
+        assert_eq!(Command::Shuffle {
+                           range: Some(Range {
+                               start: 0,
+                               end: Some(10),
+                           }),
+                       }
+                       .str()
+                       .unwrap(),
+                   "shuffle 0:10");
+    }
+
+    #[test]
+    fn add_id() {
+        use super::Position;
+
+        let add_id = |position| {
+            Command::AddId {
+                    uri: "a.flac",
+                    position: position,
+                }
+                .str()
+                .unwrap()
+        };
+
+        assert_eq!(add_id(None), r#"addid "a.flac""#);
+        assert_eq!(add_id(Some(Position::After(0))), r#"addid "a.flac" +0"#);
+        assert_eq!(add_id(Some(Position::Absolute(3))), r#"addid "a.flac" 3"#);
     }
 
-    new(try!(TcpStream::connect(addr)))
+    #[test]
+    fn stored_playlists() {
+        use super::{Position, SaveMode};
+
+        let load = |range, position| {
+            Command::Load {
+                    name: "x",
+                    range: range,
+                    position: position,
+                }
+                .str()
+                .unwrap()
+        };
+        let range = Some(Range {
+            start: 1,
+            end: Some(3),
+        });
+
+        assert_eq!(load(None, None), r#"load "x""#);
+        assert_eq!(load(range, None), r#"load "x" 1:3"#);
+        assert_eq!(load(None, Some(Position::After(0))), r#"load "x" 0: +0"#);
+        assert_eq!(load(range, Some(Position::Absolute(5))), r#"load "x" 1:3 5"#);
+
+        let save = |mode| {
+            Command::Save {
+                    name: "x",
+                    mode: mode,
+                }
+                .str()
+                .unwrap()
+        };
+
+        assert_eq!(save(None), r#"save "x""#);
+        assert_eq!(save(Some(SaveMode::Create)), r#"save "x" create"#);
+        assert_eq!(save(Some(SaveMode::Append)), r#"save "x" append"#);
+        assert_eq!(save(Some(SaveMode::Replace)), r#"save "x" replace"#);
+
+        assert_eq!(Command::PlaylistAdd {
+                           name: "x",
+                           uri: "a.flac",
+                           position: Some(2),
+                       }
+                       .str()
+                       .unwrap(),
+                   r#"playlistadd "x" "a.flac" 2"#);
+    }
+
+    #[test]
+    fn seek() {
+        assert_eq!(Command::SeekId {
+                           id: 3,
+                           time: 61.5,
+                       }
+                       .str()
+                       .unwrap(),
+                   "seekid 3 61.5");
+        assert_eq!(Command::SeekCur { time: SeekTarget::Backward(10.) }.str().unwrap(),
+                   "seekcur -10");
+        assert!(Command::SeekCur { time: SeekTarget::Forward(-1.) }.str().is_err());
+        assert!(Command::Seek {
+                        position: 0,
+                        time: ::std::f64::NAN,
+                    }
+                    .str()
+                    .is_err());
+    }
+
+    #[test]
+    fn output_set() {
+        assert_eq!(Command::OutputSet {
+                           id: 0,
+                           name: "dop",
+                           value: "1",
+                       }
+                       .str()
+                       .unwrap(),
+                   r#"outputset 0 "dop" "1""#);
+    }
+
+    #[test]
+    fn sticker_find() {
+        assert_eq!(Command::StickerFind {
+                           kind: "song",
+                           uri: "",
+                           name: "rating",
+                           filter: Some(StickerOp::Gt("3")),
+                       }
+                       .str()
+                       .unwrap(),
+                   r#"sticker find "song" "" "rating" > "3""#);
+    }
+
+    #[test]
+    fn send_message_rejects_newlines() {
+        assert!(Command::SendMessage {
+                        channel: "lights",
+                        text: "on\noff",
+                    }
+                    .str()
+                    .is_err());
+    }
 }
@@ -1,8 +1,11 @@
 //! Parsing
 
+use std::collections::BTreeMap;
+use std::fmt;
 use std::str::FromStr;
 
-use {Extra, Song, State, Status, Time};
+use {Ack, AckCode, AudioFormat, Count, Directory, Entry, Extra, Group, Message, Output, Playlist,
+     SampleFormat, Song, State, Stats, Status, Sticker, Subsystem, Time};
 
 macro_rules! parse_ty {
     ($e:expr, $ty:ty) => {
@@ -38,27 +41,35 @@ pub enum Error<'a> {
     },
 }
 
-/// Treats a parse error as a bug and panics
-pub fn bug(e: Error) -> ! {
-    use self::Error::*;
+impl<'a> fmt::Display for Error<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::Error::*;
 
-    match e {
-        ExpectedKey { key, lines } => panic!("BUG: Expected to find key {} in:\n{}", key, lines),
-        MissingKey { line } => {
-            panic!("BUG: Missing {{key}} when parsing {:?} as \"{{key}}: {{value}}\"",
-                   line)
-        }
-        MissingValue { line } => {
-            panic!("BUG: Missing {{value}} when parsing {:?} as \"{{key}}: {{value}}\"",
-                   line)
-        }
-        ParseType { ty, value } => panic!("BUG: Couldn't parse {} as {}", value, ty),
-        UnhandledKeyValuePair { key, value } => {
-            panic!("BUG: Unhandled key-value pair: ({}, {})", key, value)
+        match *self {
+            ExpectedKey { key, lines } => write!(f, "Expected to find key {} in:\n{}", key, lines),
+            MissingKey { line } => {
+                write!(f,
+                       "Missing {{key}} when parsing {:?} as \"{{key}}: {{value}}\"",
+                       line)
+            }
+            MissingValue { line } => {
+                write!(f,
+                       "Missing {{value}} when parsing {:?} as \"{{key}}: {{value}}\"",
+                       line)
+            }
+            ParseType { ty, value } => write!(f, "Couldn't parse {} as {}", value, ty),
+            UnhandledKeyValuePair { key, value } => {
+                write!(f, "Unhandled key-value pair: ({}, {})", key, value)
+            }
         }
     }
 }
 
+/// Treats a parse error as a bug and panics
+pub fn bug(e: Error) -> ! {
+    panic!("BUG: {}", e)
+}
+
 /// Parses `value` as a boolean represented as "0" or "1"
 fn parse_bool(value: &str) -> Result<bool, Error> {
     Ok(match value {
@@ -85,14 +96,21 @@ fn parse_ty<'a, T>(value: &'a str, ty: &'static str) -> Result<T, Error<'a>>
     })
 }
 
+/// Parses `line` as `{key}: {value}`
+pub fn pair(line: &str) -> Result<(&str, &str), Error> {
+    let parts = &mut line.splitn(2, ": ");
+    let k = try!(parts.next().ok_or(Error::MissingKey { line: line }));
+    let v = try!(parts.next().ok_or(Error::MissingValue { line: line }));
+
+    Ok((k, v))
+}
+
 /// Parses each line of `input` as `{key}: {value}` using the `each_line` callback
 fn parse_pairs<'a, F>(input: &'a str, mut each_line: F) -> Result<(), Error<'a>>
     where F: FnMut(&'a str, &'a str) -> Result<(), Error<'a>>
 {
     for line in input.lines() {
-        let parts = &mut line.splitn(2, ": ");
-        let k = try!(parts.next().ok_or(Error::MissingKey { line: line }));
-        let v = try!(parts.next().ok_or(Error::MissingValue { line: line }));
+        let (k, v) = try!(pair(line));
 
         try!(each_line(k, v))
     }
@@ -100,6 +118,364 @@ fn parse_pairs<'a, F>(input: &'a str, mut each_line: F) -> Result<(), Error<'a>>
     Ok(())
 }
 
+impl AckCode {
+    fn parse(input: &str) -> Result<Self, Error> {
+        use AckCode::*;
+
+        Ok(match try!(parse_ty!(input, u32)) {
+            1 => NotList,
+            2 => Arg,
+            3 => Password,
+            4 => Permission,
+            5 => Unknown,
+            50 => NoExist,
+            51 => PlaylistMax,
+            52 => System,
+            53 => PlaylistLoad,
+            54 => UpdateAlready,
+            55 => PlayerSync,
+            56 => Exist,
+            code => Other(code),
+        })
+    }
+}
+
+impl Ack {
+    /// Parses an error line: `ACK [{code}@{index}] {{{command}}} {message}`
+    pub fn parse(line: &str) -> Result<Self, Error> {
+        use self::Error::*;
+
+        let malformed = || {
+            ParseType {
+                ty: "Ack",
+                value: line,
+            }
+        };
+
+        const PREFIX: &'static str = "ACK [";
+
+        if !line.starts_with(PREFIX) {
+            return Err(malformed());
+        }
+
+        let rest = &line[PREFIX.len()..];
+        let end = try!(rest.find("] {").ok_or_else(&malformed));
+        let parts = &mut rest[..end].splitn(2, '@');
+        let code = try!(parts.next().ok_or_else(&malformed));
+        let index = try!(parts.next().ok_or_else(&malformed));
+
+        let rest = &rest[end + "] {".len()..];
+        let end = try!(rest.find('}').ok_or_else(&malformed));
+
+        Ok(Ack {
+            _0: (),
+            code: try!(AckCode::parse(code)),
+            index: try!(parse_ty!(index, u32)),
+            command: rest[..end].to_owned(),
+            message: rest[end + 1..].trim_left().to_owned(),
+        })
+    }
+}
+
+impl Subsystem {
+    /// Parses the name of a subsystem, e.g. `stored_playlist`
+    pub fn parse(input: &str) -> Result<Self, Error> {
+        use Subsystem::*;
+
+        Ok(match input {
+            "database" => Database,
+            "update" => Update,
+            "stored_playlist" => StoredPlaylist,
+            "playlist" => Playlist,
+            "player" => Player,
+            "mixer" => Mixer,
+            "output" => Output,
+            "options" => Options,
+            "partition" => Partition,
+            "sticker" => Sticker,
+            "subscription" => Subscription,
+            "message" => Message,
+            "neighbor" => Neighbor,
+            "mount" => Mount,
+            _ => {
+                return Err(Error::ParseType {
+                    ty: "Subsystem",
+                    value: input,
+                })
+            }
+        })
+    }
+}
+
+/// Parses the output of the `Idle` command: the list of changed subsystems
+///
+/// Subsystems unknown to this library are left out
+pub fn changed(input: &str) -> Result<Vec<Subsystem>, Error> {
+    let mut subsystems = vec![];
+
+    try!(parse_pairs(input, |k, v| {
+        match k {
+            "changed" => {
+                // skip the subsystems introduced by newer versions of MPD
+                if let Ok(subsystem) = Subsystem::parse(v) {
+                    subsystems.push(subsystem)
+                }
+            }
+            _ => {
+                return Err(Error::UnhandledKeyValuePair {
+                    key: k,
+                    value: v,
+                })
+            }
+        }
+
+        Ok(())
+    }));
+
+    Ok(subsystems)
+}
+
+/// Parses the output of the `Channels` command: the list of channel names
+pub fn channels(input: &str) -> Result<Vec<&str>, Error> {
+    let mut channels = vec![];
+
+    try!(parse_pairs(input, |k, v| {
+        match k {
+            "channel" => channels.push(v),
+            _ => {
+                return Err(Error::UnhandledKeyValuePair {
+                    key: k,
+                    value: v,
+                })
+            }
+        }
+
+        Ok(())
+    }));
+
+    Ok(channels)
+}
+
+/// Parses the output of the `ReadMessages` command
+pub fn messages(input: &str) -> Result<Vec<Message>, Error> {
+    let mut channel = None;
+    let mut messages = vec![];
+
+    try!(parse_pairs(input, |k, v| {
+        match k {
+            "channel" => channel = Some(v),
+            "message" => {
+                messages.push(Message {
+                    _0: (),
+                    channel: try!(channel.ok_or(Error::ExpectedKey {
+                        key: "channel",
+                        lines: input,
+                    })),
+                    text: v,
+                })
+            }
+            _ => {
+                return Err(Error::UnhandledKeyValuePair {
+                    key: k,
+                    value: v,
+                })
+            }
+        }
+
+        Ok(())
+    }));
+
+    Ok(messages)
+}
+
+/// Parses the output of the `AddId` command: the id of the added song
+pub fn id(input: &str) -> Result<u32, Error> {
+    use self::Error::*;
+
+    let mut id = Err(ExpectedKey {
+        key: "Id",
+        lines: input,
+    });
+
+    try!(parse_pairs(input, |k, v| {
+        match k {
+            "Id" => id = parse_ty!(v, u32),
+            _ => {
+                return Err(UnhandledKeyValuePair {
+                    key: k,
+                    value: v,
+                })
+            }
+        }
+
+        Ok(())
+    }));
+
+    id
+}
+
+/// Parses the output of the `List` command into a tree of groups
+///
+/// The tag of the outermost group is the first key of the output, the listed tag is the last
+/// one to appear.
+pub fn groups(input: &str) -> Result<Vec<Group>, Error> {
+    let mut groups: Vec<Group> = vec![];
+    let mut tags = vec![];
+
+    try!(parse_pairs(input, |k, v| {
+        let depth = match tags.iter().position(|&tag| tag == k) {
+            Some(depth) => depth,
+            None => {
+                tags.push(k);
+                tags.len() - 1
+            }
+        };
+
+        let mut level = &mut groups;
+        for _ in 0..depth {
+            level = match { level }.last_mut() {
+                Some(group) => &mut group.children,
+                None => {
+                    return Err(Error::UnhandledKeyValuePair {
+                        key: k,
+                        value: v,
+                    })
+                }
+            };
+        }
+
+        level.push(Group {
+            _0: (),
+            children: vec![],
+            tag: k,
+            value: v,
+        });
+
+        Ok(())
+    }));
+
+    Ok(groups)
+}
+
+/// Parses the output of the `Count` command, one `Count` per group
+pub fn counts(input: &str) -> Result<Vec<Count>, Error> {
+    let mut counts = vec![];
+
+    try!(parse_pairs(input, |k, v| {
+        match k {
+            "playtime" | "songs" => {
+                if counts.is_empty() {
+                    counts.push(Count {
+                        _0: (),
+                        group: None,
+                        playtime: 0,
+                        songs: 0,
+                    });
+                }
+
+                let count = counts.last_mut().unwrap();
+                if k == "playtime" {
+                    count.playtime = try!(parse_ty!(v, u32));
+                } else {
+                    count.songs = try!(parse_ty!(v, u32));
+                }
+            }
+            _ => {
+                counts.push(Count {
+                    _0: (),
+                    group: Some(v),
+                    playtime: 0,
+                    songs: 0,
+                })
+            }
+        }
+
+        Ok(())
+    }));
+
+    Ok(counts)
+}
+
+/// Parses the output of the `Outputs` command
+pub fn outputs(input: &str) -> Result<Vec<Output>, Error> {
+    let mut outputs: Vec<Output> = vec![];
+
+    try!(parse_pairs(input, |k, v| {
+        if k == "outputid" {
+            outputs.push(Output {
+                _0: (),
+                attributes: BTreeMap::new(),
+                enabled: false,
+                id: try!(parse_ty!(v, u32)),
+                name: "",
+                plugin: None,
+            });
+
+            return Ok(());
+        }
+
+        let output = try!(outputs.last_mut().ok_or(Error::ExpectedKey {
+            key: "outputid",
+            lines: input,
+        }));
+
+        match k {
+            "attribute" => {
+                let parts = &mut v.splitn(2, '=');
+                let name = parts.next().unwrap();
+                let value = try!(parts.next().ok_or(Error::ParseType {
+                    ty: "attribute",
+                    value: v,
+                }));
+
+                output.attributes.insert(name, value);
+            }
+            "outputenabled" => output.enabled = try!(parse_bool(v)),
+            "outputname" => output.name = v,
+            "plugin" => output.plugin = Some(v),
+            _ => {}
+        }
+
+        Ok(())
+    }));
+
+    Ok(outputs)
+}
+
+/// Parses the output of the `StickerGet`, `StickerList` and `StickerFind` commands
+///
+/// The stickers returned by `StickerFind` are preceded by the URI of their object, e.g. a `file`
+/// key for songs.
+pub fn stickers(input: &str) -> Result<Vec<Sticker>, Error> {
+    let mut stickers = vec![];
+    let mut uri = None;
+
+    try!(parse_pairs(input, |k, v| {
+        if k != "sticker" {
+            uri = Some(v);
+            return Ok(());
+        }
+
+        let parts = &mut v.splitn(2, '=');
+        let name = parts.next().unwrap();
+        let value = try!(parts.next().ok_or(Error::ParseType {
+            ty: "Sticker",
+            value: v,
+        }));
+
+        stickers.push(Sticker {
+            _0: (),
+            name: name,
+            uri: uri,
+            value: value,
+        });
+
+        Ok(())
+    }));
+
+    Ok(stickers)
+}
+
 impl State {
     fn parse(input: &str) -> Result<Self, Error> {
         use State::*;
@@ -119,40 +495,233 @@ impl State {
 }
 
 impl<'a> Song<'a> {
-    /// Parses song information as outputted by `CurrentSong` and `PlaylistInfo`
+    /// Parses song information as outputted by `CurrentSong`, `PlaylistInfo` or `LsInfo`
+    ///
+    /// Only the `file` key is mandatory
     pub fn parse(input: &'a str) -> Result<Self, Error<'a>> {
         use self::Error::*;
 
-        let expect = |k| {
-            ExpectedKey {
-                key: k,
-                lines: input,
-            }
+        let mut file = Err(ExpectedKey {
+            key: "file",
+            lines: input,
+        });
+        let mut song = Song {
+            _0: (),
+            album: None,
+            album_artist: vec![],
+            artist: vec![],
+            composer: vec![],
+            date: None,
+            disc: None,
+            duration: None,
+            file: "",
+            genre: vec![],
+            id: None,
+            last_modified: None,
+            musicbrainz_album_id: None,
+            musicbrainz_album_artist_id: None,
+            musicbrainz_artist_id: None,
+            musicbrainz_release_track_id: None,
+            musicbrainz_track_id: None,
+            musicbrainz_work_id: None,
+            name: None,
+            other: BTreeMap::new(),
+            pos: None,
+            prio: None,
+            size: None,
+            time: None,
+            title: None,
+            track: None,
         };
 
-        let mut artist = Err(expect("Artist"));
-        let mut title = Err(expect("Title"));
+        try!(parse_pairs(input, |k, v| {
+            fn first<'a>(field: &mut Option<&'a str>, value: &'a str) {
+                if field.is_none() {
+                    *field = Some(value);
+                }
+            }
+
+            match k {
+                "file" => file = Ok(v),
+                "Album" => first(&mut song.album, v),
+                "AlbumArtist" => song.album_artist.push(v),
+                "Artist" => song.artist.push(v),
+                "Composer" => song.composer.push(v),
+                "Date" => first(&mut song.date, v),
+                "Disc" => first(&mut song.disc, v),
+                "duration" => song.duration = Some(try!(parse_ty!(v, f64))),
+                "Genre" => song.genre.push(v),
+                "Id" => song.id = Some(try!(parse_ty!(v, u32))),
+                "Last-Modified" => song.last_modified = Some(v),
+                "MUSICBRAINZ_ALBUMARTISTID" => first(&mut song.musicbrainz_album_artist_id, v),
+                "MUSICBRAINZ_ALBUMID" => first(&mut song.musicbrainz_album_id, v),
+                "MUSICBRAINZ_ARTISTID" => first(&mut song.musicbrainz_artist_id, v),
+                "MUSICBRAINZ_RELEASETRACKID" => first(&mut song.musicbrainz_release_track_id, v),
+                "MUSICBRAINZ_TRACKID" => first(&mut song.musicbrainz_track_id, v),
+                "MUSICBRAINZ_WORKID" => first(&mut song.musicbrainz_work_id, v),
+                "Name" => first(&mut song.name, v),
+                "Pos" => song.pos = Some(try!(parse_ty!(v, u32))),
+                "Prio" => song.prio = Some(try!(parse_ty!(v, u8))),
+                "size" => song.size = Some(try!(parse_ty!(v, u64))),
+                "Time" => song.time = Some(try!(parse_ty!(v, u32))),
+                "Title" => first(&mut song.title, v),
+                "Track" => first(&mut song.track, v),
+                _ => song.other.entry(k).or_insert_with(Vec::new).push(v),
+            }
+
+            Ok(())
+        }));
+
+        song.file = try!(file);
+
+        Ok(song)
+    }
+}
+
+impl<'a> Directory<'a> {
+    /// Parses a directory entry, which starts with a `directory` key
+    pub fn parse(input: &'a str) -> Result<Self, Error<'a>> {
+        use self::Error::*;
+
+        let mut last_modified = None;
+        let mut path = Err(ExpectedKey {
+            key: "directory",
+            lines: input,
+        });
 
         try!(parse_pairs(input, |k, v| {
             match k {
-                "Artist" => artist = Ok(v),
-                "Title" => title = Ok(v),
+                "directory" => path = Ok(v),
+                "Last-Modified" => last_modified = Some(v),
                 _ => {}
-                // TODO uncomment
-                // _ => return Err(UnhandledKeyValuePair { key: k, value: v }),
             }
 
             Ok(())
         }));
 
-        Ok(Song {
+        Ok(Directory {
             _0: (),
-            artist: try!(artist),
-            title: try!(title),
+            last_modified: last_modified,
+            path: try!(path),
         })
     }
 }
 
+impl<'a> Playlist<'a> {
+    /// Parses a stored playlist entry, which starts with a `playlist` key
+    pub fn parse(input: &'a str) -> Result<Self, Error<'a>> {
+        use self::Error::*;
+
+        let mut last_modified = None;
+        let mut name = Err(ExpectedKey {
+            key: "playlist",
+            lines: input,
+        });
+
+        try!(parse_pairs(input, |k, v| {
+            match k {
+                "playlist" => name = Ok(v),
+                "Last-Modified" => last_modified = Some(v),
+                _ => {}
+            }
+
+            Ok(())
+        }));
+
+        Ok(Playlist {
+            _0: (),
+            last_modified: last_modified,
+            name: try!(name),
+        })
+    }
+}
+
+impl<'a> Entry<'a> {
+    /// Parses a single record, its first key determines the kind of entry
+    pub fn parse(input: &'a str) -> Result<Self, Error<'a>> {
+        let line = input.lines().next().unwrap_or("");
+
+        Ok(match try!(pair(line)).0 {
+            "directory" => Entry::Directory(try!(Directory::parse(input))),
+            "file" => Entry::Song(try!(Song::parse(input))),
+            "playlist" => Entry::Playlist(try!(Playlist::parse(input))),
+            _ => {
+                return Err(Error::ParseType {
+                    ty: "Entry",
+                    value: input,
+                })
+            }
+        })
+    }
+}
+
+/// Whether `line` starts a new record in outputs like the one of `ListAll`, i.e. whether its key
+/// is `directory`, `file` or `playlist`
+pub(crate) fn starts_record(line: &str) -> bool {
+    match line.splitn(2, ": ").next() {
+        Some("directory") | Some("file") | Some("playlist") => true,
+        _ => false,
+    }
+}
+
+/// Iterator over the records of a command output, see `records`
+pub struct Records<'a> {
+    input: &'a str,
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let input = self.input;
+
+        if input.trim().is_empty() {
+            return None;
+        }
+
+        // the first line always belongs to the current record
+        let mut start = 0;
+        while let Some(end) = input[start..].find('\n') {
+            start += end + 1;
+
+            let line = input[start..].lines().next().unwrap_or("");
+            if starts_record(line) {
+                self.input = &input[start..];
+                return Some(&input[..start - 1]);
+            }
+        }
+
+        self.input = "";
+        Some(input.trim_right_matches('\n'))
+    }
+}
+
+/// Splits the output of commands like `PlaylistInfo`, `ListAll`, `LsInfo` or `Find` into records
+///
+/// A new record starts at each `file`, `directory` or `playlist` key
+pub fn records(input: &str) -> Records {
+    Records { input: input }
+}
+
+/// Iterator over the entries of a command output, see `entries`
+pub struct Entries<'a> {
+    records: Records<'a>,
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = Result<Entry<'a>, Error<'a>>;
+
+    fn next(&mut self) -> Option<Result<Entry<'a>, Error<'a>>> {
+        self.records.next().map(Entry::parse)
+    }
+}
+
+/// Parses the output of commands like `PlaylistInfo`, `ListAll`, `LsInfo` or `Find` as a list of
+/// songs, directories and stored playlists
+pub fn entries(input: &str) -> Entries {
+    Entries { records: records(input) }
+}
+
 impl Time {
     fn parse(input: &str) -> Result<Time, Error> {
         use self::Error::*;
@@ -176,11 +745,56 @@ impl Time {
     }
 }
 
-impl Status {
-    /// Parses the output of the `Status` command
+impl AudioFormat {
+    /// Parses an audio format: `{sample_rate}:{bits}:{channels}` or `dsd{rate}:{channels}`
     pub fn parse(input: &str) -> Result<Self, Error> {
         use self::Error::*;
 
+        let malformed = || {
+            ParseType {
+                ty: "AudioFormat",
+                value: input,
+            }
+        };
+
+        let parts = &mut input.split(':');
+        let rate = try!(parts.next().ok_or_else(&malformed));
+
+        let (sample_rate, format) = if rate.starts_with("dsd") {
+            // DSD64 = 64 * 44.1 KHz bit rate
+            let rate = try!(parse_ty!(&rate["dsd".len()..], u32));
+            (try!(rate.checked_mul(44100).ok_or_else(&malformed)) / 8, SampleFormat::Dsd)
+        } else {
+            let bits = try!(parts.next().ok_or_else(&malformed));
+            let format = match bits {
+                "f" => SampleFormat::Float,
+                "dsd" => SampleFormat::Dsd,
+                _ => SampleFormat::Bits(try!(parse_ty!(bits, u8))),
+            };
+
+            (try!(parse_ty!(rate, u32)), format)
+        };
+
+        let channels = try!(parse_ty!(try!(parts.next().ok_or_else(&malformed)), u8));
+
+        if parts.next().is_some() {
+            return Err(malformed());
+        }
+
+        Ok(AudioFormat {
+            _0: (),
+            channels: channels,
+            format: format,
+            sample_rate: sample_rate,
+        })
+    }
+}
+
+impl<'a> Status<'a> {
+    /// Parses the output of the `Status` command
+    pub fn parse(input: &'a str) -> Result<Self, Error<'a>> {
+        use self::Error::*;
+
         let expect = |k| {
             ExpectedKey {
                 key: k,
@@ -188,27 +802,63 @@ impl Status {
             }
         };
 
+        let mut audio = None;
+        let mut bitrate = None;
         let mut consume = Err(expect("consume"));
+        let mut duration = None;
         let mut elapsed = None;
+        let mut error = None;
+        let mut lastloadedplaylist = None;
+        let mut mixrampdb = None;
+        let mut mixrampdelay = None;
+        let mut nextsong = None;
+        let mut nextsongid = None;
+        let mut partition = None;
+        let mut playlist = Err(expect("playlist"));
         let mut playlistlength = Err(expect("playlistlength"));
         let mut random = Err(expect("random"));
         let mut repeat = Err(expect("repeat"));
         let mut single = Err(expect("single"));
         let mut song = None;
+        let mut songid = None;
         let mut state = Err(expect("state"));
         let mut time = None;
         let mut updating_db = None;
         let mut volume = Err(expect("volume"));
+        let mut xfade = None;
 
         try!(parse_pairs(input, |k, v| {
             match k {
+                "audio" => audio = Some(try!(AudioFormat::parse(v))),
+                "bitrate" => bitrate = Some(try!(parse_ty!(v, u32))),
                 "consume" => consume = parse_bool(v),
+                "duration" => duration = Some(try!(parse_ty!(v, f64))),
                 "elapsed" => elapsed = Some(try!(parse_ty!(v, f64))),
+                "error" => error = Some(v),
+                "lastloadedplaylist" => {
+                    if !v.is_empty() {
+                        lastloadedplaylist = Some(v)
+                    }
+                }
+                "mixrampdb" => mixrampdb = Some(try!(parse_ty!(v, f64))),
+                "mixrampdelay" => {
+                    let delay = try!(parse_ty!(v, f64));
+
+                    // "nan" means that MixRamp is disabled
+                    if !delay.is_nan() {
+                        mixrampdelay = Some(delay)
+                    }
+                }
+                "nextsong" => nextsong = Some(try!(parse_ty!(v, u32))),
+                "nextsongid" => nextsongid = Some(try!(parse_ty!(v, u32))),
+                "partition" => partition = Some(v),
+                "playlist" => playlist = parse_ty!(v, u32),
                 "playlistlength" => playlistlength = parse_ty!(v, u32),
                 "random" => random = parse_bool(v),
                 "repeat" => repeat = parse_bool(v),
                 "single" => single = parse_bool(v),
                 "song" => song = Some(try!(parse_ty!(v, u32))),
+                "songid" => songid = Some(try!(parse_ty!(v, u32))),
                 "state" => state = State::parse(v),
                 "time" => time = Some(try!(Time::parse(v))),
                 "updating_db" => updating_db = Some(try!(parse_ty!(v, u32))),
@@ -219,6 +869,7 @@ impl Status {
                         volume = parse_ty!(v, u8).map(Some);
                     }
                 }
+                "xfade" => xfade = Some(try!(parse_ty!(v, u32))),
                 _ => {}
                 // TODO uncomment
                 // _ => return Err(UnhandledKeyValuePair { key: k, value: v }),
@@ -230,7 +881,11 @@ impl Status {
         let extra = song.map(|song| {
             Extra {
                 _0: (),
+                audio: audio,
+                bitrate: bitrate,
+                duration: duration,
                 elapsed: elapsed,
+                id: songid,
                 pos: song,
                 time: time,
             }
@@ -239,14 +894,343 @@ impl Status {
         Ok(Status {
             _0: (),
             consume: try!(consume),
+            error: error,
             extra: extra,
+            last_loaded_playlist: lastloadedplaylist,
+            mixramp_db: mixrampdb,
+            mixramp_delay: mixrampdelay,
+            next_song: nextsong,
+            next_song_id: nextsongid,
+            partition: partition,
             playlist_length: try!(playlistlength),
+            playlist_version: try!(playlist),
             random: try!(random),
             repeat: try!(repeat),
             single: try!(single),
             state: try!(state),
             updating_db: updating_db,
             volume: try!(volume),
+            xfade: xfade,
+        })
+    }
+}
+
+impl Stats {
+    /// Parses the output of the `Stats` command
+    pub fn parse(input: &str) -> Result<Self, Error> {
+        use self::Error::*;
+
+        let mut albums = None;
+        let mut artists = None;
+        let mut db_playtime = None;
+        let mut db_update = None;
+        let mut playtime = Err(ExpectedKey {
+            key: "playtime",
+            lines: input,
+        });
+        let mut songs = None;
+        let mut uptime = Err(ExpectedKey {
+            key: "uptime",
+            lines: input,
+        });
+
+        try!(parse_pairs(input, |k, v| {
+            match k {
+                "albums" => albums = Some(try!(parse_ty!(v, u32))),
+                "artists" => artists = Some(try!(parse_ty!(v, u32))),
+                "db_playtime" => db_playtime = Some(try!(parse_ty!(v, u64))),
+                "db_update" => db_update = Some(try!(parse_ty!(v, u64))),
+                "playtime" => playtime = parse_ty!(v, u64),
+                "songs" => songs = Some(try!(parse_ty!(v, u32))),
+                "uptime" => uptime = parse_ty!(v, u64),
+                _ => {}
+            }
+
+            Ok(())
+        }));
+
+        Ok(Stats {
+            _0: (),
+            albums: albums,
+            artists: artists,
+            db_playtime: db_playtime,
+            db_update: db_update,
+            playtime: try!(playtime),
+            songs: songs,
+            uptime: try!(uptime),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {Ack, AckCode, AudioFormat, Entry, SampleFormat, Song, State, Stats, Status, Subsystem};
+
+    #[test]
+    fn ack() {
+        let ack = Ack::parse("ACK [50@0] {play} No such song").unwrap_or_else(|e| super::bug(e));
+
+        assert_eq!(ack.code, AckCode::NoExist);
+        assert_eq!(ack.index, 0);
+        assert_eq!(ack.command, "play");
+        assert_eq!(ack.message, "No such song");
+
+        let ack = Ack::parse("ACK [2@3] {add} wrong } brace").unwrap_or_else(|e| super::bug(e));
+
+        assert_eq!(ack.code, AckCode::Arg);
+        assert_eq!(ack.index, 3);
+        assert_eq!(ack.command, "add");
+        assert_eq!(ack.message, "wrong } brace");
+    }
+
+    #[test]
+    fn ack_other_code_and_empty_command() {
+        let ack = Ack::parse("ACK [99@1] {} Unknown error").unwrap_or_else(|e| super::bug(e));
+
+        assert_eq!(ack.code, AckCode::Other(99));
+        assert_eq!(ack.index, 1);
+        assert_eq!(ack.command, "");
+        assert_eq!(ack.message, "Unknown error");
+    }
+
+    #[test]
+    fn ack_malformed() {
+        for &line in &["ACK garbage", "ACK [x@0] {play} msg", "ACK [5] {play} msg", "ACK [5@0]"] {
+            assert!(Ack::parse(line).is_err(), "{:?} was parsed", line);
+        }
+    }
+
+    #[test]
+    fn records() {
+        assert_eq!(super::records("file: a.flac\nTitle: A").collect::<Vec<_>>(),
+                   ["file: a.flac\nTitle: A"]);
+        assert_eq!(super::records("").count(), 0);
+        assert_eq!(super::records("\n").count(), 0);
+        assert_eq!(super::records("directory: a\nLast-Modified: 2020-01-01T00:00:00Z\n\
+                                   file: a/1.flac\nTitle: file\nplaylist: a/p.m3u\n\
+                                   file: b.ogg")
+                       .collect::<Vec<_>>(),
+                   ["directory: a\nLast-Modified: 2020-01-01T00:00:00Z",
+                    "file: a/1.flac\nTitle: file",
+                    "playlist: a/p.m3u",
+                    "file: b.ogg"]);
+    }
+
+    #[test]
+    fn entries() {
+        let entries = super::entries("directory: a\nLast-Modified: 2020-01-01T00:00:00Z\n\
+                                      file: a/1.flac\nplaylist: a/p.m3u")
+                          .collect::<Result<Vec<_>, _>>()
+                          .unwrap_or_else(|e| super::bug(e));
+
+        assert_eq!(entries.len(), 3);
+        match entries[0] {
+            Entry::Directory(ref directory) => {
+                assert_eq!(directory.path, "a");
+                assert_eq!(directory.last_modified, Some("2020-01-01T00:00:00Z"));
+            }
+            _ => panic!("expected a directory"),
+        }
+        match entries[1] {
+            Entry::Song(ref song) => assert_eq!(song.file, "a/1.flac"),
+            _ => panic!("expected a song"),
+        }
+        match entries[2] {
+            Entry::Playlist(ref playlist) => assert_eq!(playlist.name, "a/p.m3u"),
+            _ => panic!("expected a playlist"),
+        }
+        assert_eq!(super::entries("").count(), 0);
+    }
+
+    #[test]
+    fn song_only_requires_file() {
+        let song = Song::parse("file: a.flac").unwrap_or_else(|e| super::bug(e));
+
+        assert_eq!(song.file, "a.flac");
+        assert_eq!(song.title, None);
+        assert!(song.artist.is_empty());
+        assert!(song.other.is_empty());
+        assert!(Song::parse("Title: No file").is_err());
+    }
+
+    #[test]
+    fn song_tags() {
+        let song = Song::parse("file: a.flac\nArtist: A\nArtist: B\nGenre: Jazz\nGenre: Funk\n\
+                                Title: First\nTitle: Second\nPerformer: P\nPerformer: Q\n\
+                                Comment: C\nduration: 61.5\nTime: 62\nPos: 3\nId: 7")
+                       .unwrap_or_else(|e| super::bug(e));
+
+        assert_eq!(song.artist, ["A", "B"]);
+        assert_eq!(song.genre, ["Jazz", "Funk"]);
+        // single valued tags keep their first value
+        assert_eq!(song.title, Some("First"));
+        assert_eq!(song.other.get("Performer").map(|v| &v[..]), Some(&["P", "Q"][..]));
+        assert_eq!(song.other.get("Comment").map(|v| &v[..]), Some(&["C"][..]));
+        assert_eq!((song.duration, song.time, song.pos, song.id),
+                   (Some(61.5), Some(62), Some(3), Some(7)));
+    }
+
+    #[test]
+    fn audio_format() {
+        let format = |input| {
+            let format = AudioFormat::parse(input).unwrap_or_else(|e| super::bug(e));
+            (format.sample_rate, format.format, format.channels)
+        };
+
+        assert_eq!(format("44100:16:2"), (44100, SampleFormat::Bits(16), 2));
+        assert_eq!(format("48000:f:6"), (48000, SampleFormat::Float, 6));
+        assert_eq!(format("352800:dsd:2"), (352800, SampleFormat::Dsd, 2));
+        assert_eq!(format("dsd64:2"), (352800, SampleFormat::Dsd, 2));
+
+        for &input in &["dsd99999999:2", "dsdx:2", "44100:16", "44100:16:2:1", "44100:x:2"] {
+            assert!(AudioFormat::parse(input).is_err(), "{:?} was parsed", input);
+        }
+    }
+
+    #[test]
+    fn status() {
+        let status = Status::parse("volume: -1\nrepeat: 1\nrandom: 0\nsingle: 0\nconsume: 1\n\
+                                    playlist: 5\nplaylistlength: 3\nstate: pause\nsong: 1\n\
+                                    songid: 2\ntime: 61:200\nelapsed: 61.5\n\
+                                    audio: dsd64:2\nmixrampdelay: nan\n\
+                                    lastloadedplaylist: \nnewkey: ignored")
+                         .unwrap_or_else(|e| super::bug(e));
+
+        assert_eq!(status.volume, None);
+        assert!(status.repeat && !status.random && status.consume);
+        assert!(status.state == State::Pause);
+        assert_eq!(status.mixramp_delay, None);
+        assert_eq!(status.last_loaded_playlist, None);
+
+        let extra = status.extra.unwrap();
+        assert_eq!((extra.pos, extra.id, extra.elapsed), (1, Some(2), Some(61.5)));
+        assert_eq!(extra.audio.map(|audio| audio.format), Some(SampleFormat::Dsd));
+
+        let status = Status::parse("volume: 50\nrepeat: 0\nrandom: 0\nsingle: 0\nconsume: 0\n\
+                                    playlist: 1\nplaylistlength: 0\nstate: stop\n\
+                                    lastloadedplaylist: ")
+                         .unwrap_or_else(|e| super::bug(e));
+        assert_eq!(status.last_loaded_playlist, None);
+
+        assert!(Status::parse("volume: 50").is_err());
+    }
+
+    #[test]
+    fn id() {
+        assert_eq!(super::id("Id: 42").unwrap_or_else(|e| super::bug(e)), 42);
+        assert!(super::id("").is_err());
+        assert!(super::id("Id: 42\nvolume: 50").is_err());
+        assert!(super::id("Id: x").is_err());
+    }
+
+    #[test]
+    fn changed_skips_unknown_subsystems() {
+        assert_eq!(super::changed("changed: player\nchanged: flux_capacitor\nchanged: mixer")
+                       .unwrap_or_else(|e| super::bug(e)),
+                   [Subsystem::Player, Subsystem::Mixer]);
+        assert!(super::changed("volume: 50").is_err());
+    }
+
+    #[test]
+    fn groups() {
+        let groups = super::groups("AlbumArtist: A\nDate: 1999\nAlbum: X\nAlbum: Y\n\
+                                    AlbumArtist: B\nDate: \nAlbum: Z\n")
+                         .unwrap_or_else(|e| super::bug(e));
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!((groups[0].tag, groups[0].value), ("AlbumArtist", "A"));
+        assert_eq!(groups[0].children[0].value, "1999");
+        assert_eq!(groups[0].children[0].children.len(), 2);
+        assert_eq!(groups[1].children[0].value, "");
+        assert_eq!(groups[1].children[0].children[0].value, "Z");
+
+        let groups = super::groups("AlbumArtist: A\nAlbum: X\nAlbum: ")
+                         .unwrap_or_else(|e| super::bug(e));
+
+        assert_eq!(groups[0].children.iter().map(|g| g.value).collect::<Vec<_>>(), ["X", ""]);
+    }
+
+    #[test]
+    fn counts() {
+        let counts = super::counts("Genre: Jazz\nsongs: 3\nplaytime: 900\nGenre: \nsongs: 1\n\
+                                    playtime: 60\n")
+                         .unwrap_or_else(|e| super::bug(e));
+
+        assert_eq!(counts.iter().map(|c| (c.group, c.songs, c.playtime)).collect::<Vec<_>>(),
+                   [(Some("Jazz"), 3, 900), (Some(""), 1, 60)]);
+
+        let counts = super::counts("songs: 4\nplaytime: 960\n").unwrap_or_else(|e| super::bug(e));
+
+        assert_eq!(counts.iter().map(|c| (c.group, c.songs, c.playtime)).collect::<Vec<_>>(),
+                   [(None, 4, 960)]);
+    }
+
+    #[test]
+    fn outputs() {
+        let outputs = super::outputs("outputid: 0\noutputname: ALSA\nplugin: alsa\n\
+                                      outputenabled: 1\nattribute: dop=0\noutputid: 1\n\
+                                      outputname: Stream\noutputenabled: 0\n")
+                          .unwrap_or_else(|e| super::bug(e));
+
+        assert_eq!(outputs.len(), 2);
+        assert_eq!((outputs[0].id, outputs[0].name, outputs[0].plugin, outputs[0].enabled),
+                   (0, "ALSA", Some("alsa"), true));
+        assert_eq!(outputs[0].attributes.get("dop"), Some(&"0"));
+        assert_eq!((outputs[1].id, outputs[1].plugin, outputs[1].enabled),
+                   (1, None, false));
+        assert!(super::outputs("outputname: ALSA\n").is_err());
+    }
+
+    #[test]
+    fn stats() {
+        let stats = Stats::parse("uptime: 4\nplaytime: 2\nartists: 3\nalbums: 1\nsongs: 9\n\
+                                  db_playtime: 2100\ndb_update: 1700000000\n")
+                        .unwrap_or_else(|e| super::bug(e));
+
+        assert_eq!((stats.uptime, stats.playtime), (4, 2));
+        assert_eq!((stats.artists, stats.albums, stats.songs), (Some(3), Some(1), Some(9)));
+        assert_eq!((stats.db_playtime, stats.db_update), (Some(2100), Some(1700000000)));
+
+        // no database configured
+        let stats = Stats::parse("uptime: 4\nplaytime: 0\n").unwrap_or_else(|e| super::bug(e));
+
+        assert_eq!(stats.songs, None);
+        assert!(Stats::parse("playtime: 0\n").is_err());
+    }
+
+    #[test]
+    fn stickers() {
+        let stickers = super::stickers("file: a.flac\nsticker: rating=5\nfile: b.flac\n\
+                                        sticker: comment=a=b\n")
+                           .unwrap_or_else(|e| super::bug(e));
+
+        assert_eq!(stickers.len(), 2);
+        assert_eq!((stickers[0].uri, stickers[0].name, stickers[0].value),
+                   (Some("a.flac"), "rating", "5"));
+        assert_eq!((stickers[1].uri, stickers[1].name, stickers[1].value),
+                   (Some("b.flac"), "comment", "a=b"));
+
+        let stickers = super::stickers("sticker: rating=5\n").unwrap_or_else(|e| super::bug(e));
+
+        assert_eq!(stickers[0].uri, None);
+        assert!(super::stickers("sticker: rating\n").is_err());
+    }
+
+    #[test]
+    fn messages() {
+        let messages = super::messages("channel: lights\nmessage: on\nmessage: dim 50\n\
+                                        channel: door\nmessage: ring\n")
+                           .unwrap_or_else(|e| super::bug(e));
+
+        assert_eq!(messages.iter().map(|m| (m.channel, m.text)).collect::<Vec<_>>(),
+                   [("lights", "on"), ("lights", "dim 50"), ("door", "ring")]);
+        assert!(super::messages("message: orphan\n").is_err());
+    }
+
+    #[test]
+    fn channels() {
+        assert_eq!(super::channels("channel: lights\nchannel: door\n")
+                       .unwrap_or_else(|e| super::bug(e)),
+                   ["lights", "door"]);
+    }
+}